@@ -0,0 +1,533 @@
+//! Diagnostic bundles: the audio subsystem's read-only state in one archive
+//!
+//! A bundle is a tar archive laid out as a filesystem root: `/proc/asound`,
+//! the sysfs devices behind `/sys/class/sound` with their drivers, the sound
+//! modules' parameters, the ALSA configuration files and `/proc/version`, plus
+//! `bundle.json` (versions) and `audio-interrogator.json` (the report at the
+//! time of recording). Extracted, it is a tree `--sysroot` can read, which is
+//! how [`Bundle`] replays an interrogation offline.
+//!
+//! The archive is plain tar with GNU long names, so `tar -xf` unpacks it too.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::devices::{self, alsa_devices::ProcBackend, BackendRegistry, DeviceBackend, ProbeOptions};
+use crate::sysroot::SysRoot;
+use crate::{AudioDeviceInfo, SystemAudioInfo};
+
+/// Name of the manifest inside the archive
+pub const MANIFEST_FILE: &str = "bundle.json";
+/// Name of the recorded report inside the archive
+pub const REPORT_FILE: &str = "audio-interrogator.json";
+
+/// Largest file copied into a bundle; sysfs and procfs files are far smaller
+const MAX_FILE_SIZE: u64 = 1 << 20;
+
+/// Device attributes that are huge, slow or side-effecting to read
+const SKIPPED_ATTRIBUTES: [&str; 6] = ["config", "rom", "vpd", "remove", "rescan", "reset"];
+
+/// What a bundle was recorded with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub tool_version: String,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    /// `/proc/version`
+    pub kernel: Option<String>,
+    /// `/proc/asound/version`
+    pub alsa_driver: Option<String>,
+    /// Version of the alsa-lib the tool was running against
+    pub alsa_lib: Option<String>,
+    /// Files and symlinks copied, not counting the manifest and report
+    pub files: usize,
+}
+
+/// One archive member
+enum Entry {
+    File { path: String, data: Vec<u8> },
+    Symlink { path: String, target: String },
+}
+
+impl Entry {
+    fn path(&self) -> &str {
+        match self {
+            Entry::File { path, .. } | Entry::Symlink { path, .. } => path,
+        }
+    }
+}
+
+/// Copy the state below `root` and the recorded `report` into a bundle at `path`
+pub fn write_bundle(path: &Path, root: &SysRoot, report: &SystemAudioInfo) -> Result<BundleManifest> {
+    let mut entries = Vec::new();
+    collect_tree(&root.asound(), "proc/asound", &mut entries);
+    collect_file(&root.proc().join("version"), "proc/version", &mut entries);
+    collect_sysfs(&root.sys(), &mut entries);
+    collect_alsa_config(root, &mut entries);
+
+    // The same file can be reached twice, e.g. an ancestor of two cards
+    let mut seen = BTreeSet::new();
+    entries.retain(|e| seen.insert(e.path().to_string()));
+
+    let read_trimmed = |path: PathBuf| fs::read_to_string(path).ok().map(|v| v.trim().to_string());
+    let manifest = BundleManifest {
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        kernel: read_trimmed(root.proc().join("version")),
+        alsa_driver: read_trimmed(root.asound().join("version")),
+        alsa_lib: if root.is_host() { alsa_lib_version() } else { None },
+        files: entries.len(),
+    };
+
+    let mut out = std::io::BufWriter::new(fs::File::create(path)
+        .with_context(|| format!("cannot create {}", path.display()))?);
+    let mtime = manifest.created;
+    write_entry(&mut out, &Entry::File { path: MANIFEST_FILE.to_string(), data: serde_json::to_vec_pretty(&manifest)? }, mtime)?;
+    write_entry(&mut out, &Entry::File { path: REPORT_FILE.to_string(), data: serde_json::to_vec_pretty(report)? }, mtime)?;
+    for entry in &entries {
+        write_entry(&mut out, entry, mtime)?;
+    }
+    // End of archive: two zero blocks
+    out.write_all(&[0; 1024])?;
+    out.flush()?;
+
+    Ok(manifest)
+}
+
+/// Copy a directory tree, keeping symlinks as symlinks
+fn collect_tree(dir: &Path, prefix: &str, entries: &mut Vec<Entry>) {
+    let Ok(children) = fs::read_dir(dir) else { return };
+    for child in children.flatten() {
+        let name = child.file_name();
+        let path = format!("{}/{}", prefix, name.to_string_lossy());
+        match child.file_type() {
+            Ok(t) if t.is_dir() => collect_tree(&child.path(), &path, entries),
+            Ok(_) => collect_file(&child.path(), &path, entries),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Copy one file or symlink; unreadable files (write-only attributes) are skipped
+fn collect_file(source: &Path, path: &str, entries: &mut Vec<Entry>) {
+    let Ok(metadata) = fs::symlink_metadata(source) else { return };
+    if metadata.file_type().is_symlink() {
+        if let Ok(target) = fs::read_link(source) {
+            entries.push(Entry::Symlink { path: path.to_string(), target: target.to_string_lossy().into_owned() });
+        }
+        return;
+    }
+    // procfs and sysfs report a size of 0 or 4096 regardless of content, so read to a cap
+    let mut data = Vec::new();
+    let read = fs::File::open(source).and_then(|f| f.take(MAX_FILE_SIZE).read_to_end(&mut data));
+    if read.is_ok() {
+        entries.push(Entry::File { path: path.to_string(), data });
+    }
+}
+
+/// Copy every `/sys/class/sound` node with the device directories above it,
+/// the drivers bound to them and the parameters of the sound modules
+fn collect_sysfs(sys: &Path, entries: &mut Vec<Entry>) {
+    let Ok(sys_real) = fs::canonicalize(sys) else { return };
+    let Ok(nodes) = fs::read_dir(sys.join("class/sound")) else { return };
+
+    let mut device_dirs = BTreeSet::new();
+    let mut modules = BTreeSet::new();
+    for node in nodes.flatten() {
+        collect_file(&node.path(), &format!("sys/class/sound/{}", node.file_name().to_string_lossy()), entries);
+        let Ok(real) = fs::canonicalize(node.path()) else { continue };
+        // Every directory from the node up to, but not including, /sys/devices
+        for dir in real.ancestors().take_while(|dir| dir.starts_with(sys_real.join("devices")) && *dir != sys_real.join("devices")) {
+            device_dirs.insert(dir.to_path_buf());
+        }
+    }
+
+    for dir in &device_dirs {
+        let Ok(relative) = dir.strip_prefix(&sys_real) else { continue };
+        let prefix = format!("sys/{}", relative.display());
+        let Ok(children) = fs::read_dir(dir) else { continue };
+        for child in children.flatten() {
+            let name = child.file_name().to_string_lossy().into_owned();
+            let is_dir = child.file_type().is_ok_and(|t| t.is_dir());
+            if is_dir || SKIPPED_ATTRIBUTES.contains(&name.as_str()) || name.starts_with("resource") {
+                continue;
+            }
+            collect_file(&child.path(), &format!("{}/{}", prefix, name), entries);
+        }
+
+        // The driver's module link is what names the kernel module
+        let Ok(driver) = fs::canonicalize(dir.join("driver")) else { continue };
+        if let Ok(relative) = driver.strip_prefix(&sys_real) {
+            collect_file(&driver.join("module"), &format!("sys/{}/module", relative.display()), entries);
+        }
+        if let Some(module) = fs::read_link(driver.join("module")).ok().and_then(|m| m.file_name().map(|n| n.to_string_lossy().into_owned())) {
+            modules.insert(module);
+        }
+    }
+
+    if let Ok(loaded) = fs::read_dir(sys.join("module")) {
+        modules.extend(loaded.flatten()
+            .map(|m| m.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("snd") || name == "soundcore"));
+    }
+    for module in modules {
+        collect_tree(&sys.join("module").join(&module).join("parameters"), &format!("sys/module/{}/parameters", module), entries);
+    }
+}
+
+/// Copy the configuration files alsa-lib reads, following symlinks so the
+/// contents end up in the bundle
+fn collect_alsa_config(root: &SysRoot, entries: &mut Vec<Entry>) {
+    let mut paths: Vec<PathBuf> = ["etc/asound.conf", "usr/share/alsa/alsa.conf", "var/lib/alsa/asound.state"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    if let Ok(conf_d) = fs::read_dir(root.root().join("etc/alsa/conf.d")) {
+        paths.extend(conf_d.flatten().map(|f| Path::new("etc/alsa/conf.d").join(f.file_name())));
+    }
+    // Per-user files only exist on the running system
+    if root.is_host() {
+        if let Ok(home) = std::env::var("HOME") {
+            let home = Path::new(home.trim_start_matches('/'));
+            paths.push(home.join(".asoundrc"));
+            paths.push(home.join(".config/alsa/asoundrc"));
+        }
+    }
+
+    for path in paths {
+        if let Ok(data) = fs::read(root.root().join(&path)) {
+            entries.push(Entry::File { path: path.to_string_lossy().into_owned(), data });
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn alsa_lib_version() -> Option<String> {
+    // SAFETY: alsa-lib returns a pointer to a static NUL-terminated string
+    let version = unsafe { std::ffi::CStr::from_ptr(alsa_sys::snd_asoundlib_version()) };
+    Some(version.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn alsa_lib_version() -> Option<String> {
+    // ALSA is Linux-specific
+    None
+}
+
+/// Write one tar member, preceded by GNU long-name records when needed
+fn write_entry(out: &mut impl Write, entry: &Entry, mtime: u64) -> Result<()> {
+    let (path, kind, target, data): (&str, u8, &str, &[u8]) = match entry {
+        Entry::File { path, data } => (path, b'0', "", data),
+        Entry::Symlink { path, target } => (path, b'2', target, &[]),
+    };
+
+    if target.len() >= 100 {
+        write_member(out, "././@LongLink", b'K', "", target.as_bytes(), mtime)?;
+    }
+    if path.len() >= 100 {
+        write_member(out, "././@LongLink", b'L', "", path.as_bytes(), mtime)?;
+    }
+    write_member(out, path, kind, target, data, mtime)
+}
+
+fn write_member(out: &mut impl Write, name: &str, kind: u8, link: &str, data: &[u8], mtime: u64) -> Result<()> {
+    let mut header = [0u8; 512];
+    let field = |header: &mut [u8; 512], offset: usize, len: usize, value: &[u8]| {
+        let n = value.len().min(len);
+        header[offset..offset + n].copy_from_slice(&value[..n]);
+    };
+    let octal = |value: u64, width: usize| format!("{:0width$o}", value, width = width - 1);
+
+    field(&mut header, 0, 100, name.as_bytes());
+    field(&mut header, 100, 8, octal(if kind == b'2' { 0o777 } else { 0o644 }, 8).as_bytes());
+    field(&mut header, 108, 8, octal(0, 8).as_bytes());
+    field(&mut header, 116, 8, octal(0, 8).as_bytes());
+    field(&mut header, 124, 12, octal(data.len() as u64, 12).as_bytes());
+    field(&mut header, 136, 12, octal(mtime, 12).as_bytes());
+    header[156] = kind;
+    field(&mut header, 157, 100, link.as_bytes());
+    field(&mut header, 257, 8, b"ustar  \0");
+    field(&mut header, 265, 32, b"root");
+    field(&mut header, 297, 32, b"root");
+
+    // The checksum is computed with its own field set to spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    field(&mut header, 148, 8, format!("{:06o}\0 ", checksum).as_bytes());
+
+    out.write_all(&header)?;
+    out.write_all(data)?;
+    out.write_all(&vec![0; (512 - data.len() % 512) % 512])?;
+    Ok(())
+}
+
+/// Read every member of a tar archive
+fn read_entries(content: &[u8]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut long_name = None;
+    let mut long_link = None;
+    let mut offset = 0;
+
+    while offset + 512 <= content.len() {
+        let header = &content[offset..offset + 512];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let text = |start: usize, len: usize| {
+            let raw = &header[start..start + len];
+            let end = raw.iter().position(|&b| b == 0).unwrap_or(len);
+            String::from_utf8_lossy(&raw[..end]).into_owned()
+        };
+        let size = u64::from_str_radix(text(124, 12).trim(), 8).context("corrupt tar header")? as usize;
+        let data = content.get(offset + 512..offset + 512 + size).context("truncated tar archive")?;
+        offset += 512 + size.div_ceil(512) * 512;
+
+        let nul_terminated = |data: &[u8]| String::from_utf8_lossy(data).trim_end_matches('\0').to_string();
+        let mut path = long_name.take().unwrap_or_else(|| match text(345, 155) {
+            // ustar splits long names into a prefix and a name
+            prefix if !prefix.is_empty() && header[257..262] == b"ustar"[..] && header[262] == 0 => format!("{}/{}", prefix, text(0, 100)),
+            _ => text(0, 100),
+        });
+        path = path.trim_start_matches("./").to_string();
+        match header[156] {
+            b'L' => long_name = Some(nul_terminated(data)),
+            b'K' => long_link = Some(nul_terminated(data)),
+            b'0' | 0 => entries.push(Entry::File { path, data: data.to_vec() }),
+            b'2' => entries.push(Entry::Symlink { path, target: long_link.take().unwrap_or_else(|| text(157, 100)) }),
+            // Directories are created as needed; nothing else occurs in a bundle
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Where `path` from an archive lands below `dir`, refusing anything that
+/// would escape it: absolute paths, `..`, or a path through a symlink
+fn extraction_path(dir: &Path, path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        bail!("bundle member {} points outside the bundle", path);
+    }
+    let target = dir.join(relative);
+    // An earlier member at the same path could be a symlink to anywhere
+    if fs::symlink_metadata(&target).is_ok() {
+        bail!("bundle member {} already exists", path);
+    }
+    for parent in target.ancestors().skip(1).take_while(|a| *a != dir) {
+        if fs::symlink_metadata(parent).is_ok_and(|m| m.file_type().is_symlink()) {
+            bail!("bundle member {} is below a symlink", path);
+        }
+    }
+    Ok(target)
+}
+
+/// Unpack archive members into `dir`
+fn extract(entries: &[Entry], dir: &Path) -> Result<()> {
+    for entry in entries {
+        let target = extraction_path(dir, entry.path())?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match entry {
+            Entry::File { data, .. } => {
+                fs::OpenOptions::new().write(true).create_new(true).open(&target)?.write_all(data)?;
+            }
+            #[cfg(unix)]
+            Entry::Symlink { target: link, .. } => std::os::unix::fs::symlink(link, &target)?,
+            #[cfg(not(unix))]
+            Entry::Symlink { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+/// A bundle unpacked into a temporary directory, removed again on drop
+pub struct Bundle {
+    dir: PathBuf,
+    pub manifest: Option<BundleManifest>,
+    /// The report recorded together with the bundle
+    pub report: Option<SystemAudioInfo>,
+}
+
+impl Bundle {
+    /// Unpack the bundle at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        static OPENED: AtomicUsize = AtomicUsize::new(0);
+
+        let content = fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let entries = read_entries(&content).with_context(|| format!("{} is not a bundle", path.display()))?;
+
+        // Never reuse a directory someone else may have prepared in the shared temp dir
+        let dir = loop {
+            let dir = std::env::temp_dir().join(format!(
+                "audio-interrogator-bundle-{}-{}", std::process::id(), OPENED.fetch_add(1, Ordering::Relaxed)));
+            match fs::create_dir(&dir) {
+                Ok(()) => break dir,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e).with_context(|| format!("cannot create {}", dir.display())),
+            }
+        };
+        // Created before extracting so the directory is removed if that fails
+        let mut bundle = Self { dir, manifest: None, report: None };
+        extract(&entries, &bundle.dir)?;
+
+        let read_json = |name: &str| fs::read(bundle.dir.join(name)).ok();
+        bundle.manifest = read_json(MANIFEST_FILE).map(|m| serde_json::from_slice(&m)).transpose()
+            .context("invalid bundle manifest")?;
+        bundle.report = read_json(REPORT_FILE).map(|r| serde_json::from_slice(&r)).transpose()
+            .context("invalid recorded report")?;
+        Ok(bundle)
+    }
+
+    /// The unpacked tree, to read `/proc` and `/sys` from
+    pub fn sysroot(&self) -> SysRoot {
+        SysRoot::new(&self.dir)
+    }
+
+    /// The recorded devices followed by the `/proc/asound` scanner
+    pub fn registry(&self) -> BackendRegistry {
+        let mut registry = BackendRegistry::new();
+        registry.register(RecordedBackend(self.report.as_ref().map(|r| r.devices.clone()).unwrap_or_default()));
+        registry.register(ProcBackend);
+        registry
+    }
+
+    /// Replay the interrogation from the bundle
+    ///
+    /// Devices come from the recorded report, re-read from the bundle's
+    /// `/proc/asound` and `/sys` where those have them; cards are parsed anew,
+    /// and the defaults are the recorded ones.
+    pub fn interrogate(&self, registry: &BackendRegistry, no_proc: bool) -> SystemAudioInfo {
+        let mut info = devices::interrogate(registry, &ProbeOptions { no_proc, sysroot: self.sysroot() });
+        if let Some(ref report) = self.report {
+            info.default_input = report.default_input.clone();
+            info.default_input_source = report.default_input_source;
+            info.default_output = report.default_output.clone();
+            info.default_output_source = report.default_output_source;
+        }
+        info
+    }
+}
+
+impl Drop for Bundle {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Devices the live backends reported when the bundle was recorded
+pub struct RecordedBackend(pub Vec<AudioDeviceInfo>);
+
+impl DeviceBackend for RecordedBackend {
+    fn name(&self) -> &str {
+        "recorded"
+    }
+
+    fn check_available(&self, _options: &ProbeOptions) -> Result<()> {
+        if self.0.is_empty() {
+            bail!("the bundle has no recorded report");
+        }
+        Ok(())
+    }
+
+    fn enumerate(&self, _options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Print what a freshly written bundle contains
+pub fn print_manifest(path: &Path, manifest: &BundleManifest) {
+    let size = fs::metadata(path).map_or(0, |m| m.len());
+    println!("┌─ Bundle: {}", path.display());
+    println!("├─ Files: {} ({} KiB)", manifest.files, size.div_ceil(1024));
+    if let Some(ref kernel) = manifest.kernel {
+        println!("├─ Kernel: {}", kernel);
+    }
+    if let Some(ref driver) = manifest.alsa_driver {
+        println!("├─ ALSA Driver: {}", driver);
+    }
+    if let Some(ref lib) = manifest.alsa_lib {
+        println!("├─ ALSA Library: {}", lib);
+    }
+    println!("└─ Replay with: audio-interrogator --from-bundle {}", path.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_round_trip_replays_recorded_tree() {
+        let fixture = SysRoot::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysroot/usb"));
+        let options = ProbeOptions { sysroot: fixture.clone(), ..Default::default() };
+        let recorded = devices::interrogate(&BackendRegistry::with_builtin(), &options);
+
+        let path = std::env::temp_dir().join(format!("audio-interrogator-test-{}.tar", std::process::id()));
+        let manifest = write_bundle(&path, &fixture, &recorded).unwrap();
+        assert!(manifest.files > 20);
+        assert_eq!(manifest.alsa_lib, None);
+
+        let bundle = Bundle::open(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(bundle.manifest.as_ref().map(|m| m.files), Some(manifest.files));
+
+        // sysfs symlinks survive, so the card identity resolves inside the bundle
+        let info = bundle.interrogate(&bundle.registry(), false);
+        let scarlett = info.find_device("hw:1,0").unwrap();
+        assert_eq!(scarlett.stable_id.as_deref(), Some("usb-1235_8210-Y8XXXXX0123456-pcm0"));
+        assert_eq!(scarlett.hardware.as_ref().and_then(|h| h.kernel_module.as_deref()), Some("snd_usb_audio"));
+        assert!(scarlett.usb_stream.is_some() && scarlett.in_use);
+        assert_eq!(info.cards[0].id, "USB");
+        assert_eq!((info.backends[0].devices, info.backends[1].duplicates), (1, 1));
+
+        let dir = bundle.sysroot().root().to_path_buf();
+        drop(bundle);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn long_names_and_escaping_members() {
+        let long = format!("sys/devices/{}/sound/card2/id", "usb1/1-2/1-2.4/1-2.4.3".repeat(8));
+        let mut archive = Vec::new();
+        write_entry(&mut archive, &Entry::File { path: long.clone(), data: b"Dock\n".to_vec() }, 0).unwrap();
+        write_entry(&mut archive, &Entry::Symlink { path: "sys/class/sound/card2".to_string(), target: format!("../../{}", &long[4..]) }, 0).unwrap();
+        let entries = read_entries(&archive).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(entries[0], Entry::File { ref path, ref data } if *path == long && data == b"Dock\n"));
+        assert!(matches!(entries[1], Entry::Symlink { ref target, .. } if target.len() > 100));
+
+        let dir = Path::new("/nonexistent");
+        assert!(extraction_path(dir, "../etc/passwd").is_err());
+        assert!(extraction_path(dir, "/etc/passwd").is_err());
+        assert_eq!(extraction_path(dir, "proc/asound/cards").unwrap(), dir.join("proc/asound/cards"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn member_cannot_write_through_an_earlier_symlink() {
+        let dir = std::env::temp_dir().join(format!("audio-interrogator-test-{}-extract", std::process::id()));
+        let outside = std::env::temp_dir().join(format!("audio-interrogator-test-{}-outside", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        fs::write(&outside, "untouched").unwrap();
+
+        let entries = [
+            Entry::Symlink { path: "etc/asound.conf".to_string(), target: outside.display().to_string() },
+            Entry::File { path: "etc/asound.conf".to_string(), data: b"pcm.!default null".to_vec() },
+        ];
+        let result = extract(&entries, &dir);
+        let written = fs::read_to_string(&outside).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_file(&outside);
+
+        assert!(result.is_err());
+        assert_eq!(written, "untouched");
+    }
+}
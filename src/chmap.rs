@@ -0,0 +1,123 @@
+//! PCM channel maps: which speaker position each channel drives
+//!
+//! A channel count alone does not say whether eight channels are 7.1
+//! surround or eight independent line outputs. Drivers that know advertise
+//! the maps they support per channel count, and whether a map is fixed,
+//! freely assignable (variable) or assignable in pairs.
+
+use serde::{Deserialize, Serialize};
+
+/// One channel map a PCM supports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelMap {
+    /// "fixed", "variable" or "paired"
+    pub map_type: String,
+    /// Position per channel, e.g. ["FL", "FR", "FC", "LFE"]
+    pub positions: Vec<String>,
+    /// Common speaker layout these positions form, e.g. "5.1"
+    pub layout: Option<String>,
+}
+
+/// Channel maps of one PCM stream direction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelMaps {
+    pub supported: Vec<ChannelMap>,
+    /// Map in effect now, if the driver reports one
+    pub current: Option<Vec<String>>,
+}
+
+/// Query supported and current maps of an open PCM
+///
+/// The positions are taken from alsa-lib's printed form: the alsa crate's
+/// position enum lacks FC, LFE, RR and SL and cannot convert such maps.
+#[cfg(target_os = "linux")]
+pub fn read_channel_maps(pcm: &alsa::PCM) -> ChannelMaps {
+    use alsa::pcm::ChmapType;
+
+    let supported = pcm.query_chmaps()
+        .filter_map(|(map_type, map)| {
+            let map_type = match map_type {
+                ChmapType::Fixed => "fixed",
+                ChmapType::Var => "variable",
+                ChmapType::Paired => "paired",
+                ChmapType::None => return None,
+            };
+            Some(channel_map(map_type, &map.to_string()))
+        })
+        .collect();
+
+    // Drivers report all-unknown positions while the stream is not configured
+    let current = pcm.get_chmap().ok()
+        .map(|map| parse_positions(&map.to_string()))
+        .filter(|positions| !positions.is_empty() && positions.iter().any(|p| p != "UNKNOWN"));
+
+    ChannelMaps { supported, current }
+}
+
+/// Build a map from alsa-lib's printed positions ("FL FR FC LFE")
+pub fn channel_map(map_type: &str, printed: &str) -> ChannelMap {
+    let positions = parse_positions(printed);
+    ChannelMap {
+        map_type: map_type.to_string(),
+        layout: layout_name(&positions).map(str::to_string),
+        positions,
+    }
+}
+
+fn parse_positions(printed: &str) -> Vec<String> {
+    printed.split_whitespace().map(str::to_string).collect()
+}
+
+/// Name the speaker layout a set of positions forms, regardless of order
+pub fn layout_name(positions: &[String]) -> Option<&'static str> {
+    let mut sorted: Vec<&str> = positions.iter().map(String::as_str).collect();
+    sorted.sort_unstable();
+
+    const LAYOUTS: [(&str, &[&str]); 7] = [
+        ("mono", &["MONO"]),
+        ("stereo", &["FL", "FR"]),
+        ("2.1", &["FL", "FR", "LFE"]),
+        ("quad", &["FL", "FR", "RL", "RR"]),
+        ("5.1", &["FC", "FL", "FR", "LFE", "RL", "RR"]),
+        ("5.1 (side)", &["FC", "FL", "FR", "LFE", "SL", "SR"]),
+        ("7.1", &["FC", "FL", "FR", "LFE", "RL", "RR", "SL", "SR"]),
+    ];
+    if let Some((name, _)) = LAYOUTS.iter().find(|(_, layout)| *layout == sorted.as_slice()) {
+        return Some(name);
+    }
+
+    // Interfaces label their line outputs as auxiliary channels
+    if !sorted.is_empty() && sorted.iter().all(|p| p.starts_with("AUX")) {
+        return Some("discrete");
+    }
+    None
+}
+
+impl ChannelMap {
+    /// "fixed 5.1: FL FR RL RR FC LFE"
+    pub fn describe(&self) -> String {
+        match self.layout {
+            Some(ref layout) => format!("{} {}: {}", self.map_type, layout, self.positions.join(" ")),
+            None => format!("{} {}ch: {}", self.map_type, self.positions.len(), self.positions.join(" ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_common_layouts() {
+        assert_eq!(channel_map("fixed", "FL FR").layout.as_deref(), Some("stereo"));
+        assert_eq!(channel_map("variable", "FL FR RL RR FC LFE SL SR").layout.as_deref(), Some("7.1"));
+        assert_eq!(channel_map("paired", "FL FR FC LFE RL RR").layout.as_deref(), Some("5.1"));
+        assert_eq!(channel_map("fixed", "AUX0 AUX1 AUX2 AUX3").layout.as_deref(), Some("discrete"));
+        assert_eq!(channel_map("fixed", "FL FR NA NA").layout, None);
+
+        let map = channel_map("fixed", "FL FR FC LFE RL RR");
+        assert_eq!(map.positions.len(), 6);
+        assert_eq!(map.describe(), "fixed 5.1: FL FR FC LFE RL RR");
+        assert_eq!(channel_map("fixed", "FL FR NA NA").describe(), "fixed 4ch: FL FR NA NA");
+    }
+}
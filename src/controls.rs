@@ -0,0 +1,625 @@
+//! Inventory of ALSA control elements with their ranges and dB scales
+//!
+//! Every card exposes its mixer, switches, routing enums and driver specific
+//! settings as control elements. This module lists each element with its
+//! type, value range, enum items, access flags and current values, and reads
+//! the element's TLV data so volumes can be given in dB.
+//!
+//! The alsa crate does not expose element ranges, enum items or TLV data, so
+//! the control interface is used through alsa-sys directly.
+
+use serde::{Deserialize, Serialize};
+
+/// All control elements of one card
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardControls {
+    pub card: u32,
+    /// Short card id, e.g. "PCH"
+    pub id: String,
+    pub controls: Vec<ControlElement>,
+}
+
+/// One control element
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ControlElement {
+    pub numid: u32,
+    /// CARD, HWDEP, MIXER, PCM, RAWMIDI, TIMER or SEQUENCER
+    pub interface: String,
+    pub name: String,
+    pub index: u32,
+    pub device: u32,
+    pub subdevice: u32,
+    /// BOOLEAN, INTEGER, INTEGER64, ENUMERATED, BYTES or IEC958
+    pub element_type: String,
+    /// Number of values (channels for most volumes and switches)
+    pub count: u32,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub step: Option<i64>,
+    pub enum_items: Vec<String>,
+    /// Current values; absent for write-only or unreadable elements
+    pub values: Option<ControlValues>,
+    pub access: ControlAccess,
+    /// TLV type, e.g. DB_SCALE, DB_RANGE, DB_MINMAX, CHMAP_FIXED
+    pub tlv_type: Option<String>,
+    pub db_range: Option<DbRange>,
+    /// Current values converted to dB; `None` entries are muted
+    pub values_db: Vec<Option<f64>>,
+}
+
+/// Current values, typed by the element
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ControlValues {
+    Boolean(Vec<bool>),
+    Integer(Vec<i64>),
+    /// Selected item names
+    Enumerated(Vec<String>),
+    /// Hex dump of BYTES elements (IEC958 values are not read)
+    Bytes(String),
+}
+
+/// Access flags of an element
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlAccess {
+    pub readable: bool,
+    pub writable: bool,
+    /// The value can change without a write (meters, jack state)
+    pub volatile: bool,
+    pub inactive: bool,
+    pub locked: bool,
+    /// Created by an application rather than the driver
+    pub user: bool,
+    pub tlv_readable: bool,
+}
+
+/// Gain range of a volume element
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DbRange {
+    pub min_db: f64,
+    pub max_db: f64,
+    /// The minimum value mutes rather than attenuates
+    pub min_is_mute: bool,
+}
+
+/// Read the controls of every card, optionally restricted to one
+#[cfg(target_os = "linux")]
+pub fn read_all_controls(card_filter: Option<u32>) -> Vec<CardControls> {
+    let mut cards = Vec::new();
+
+    for card in alsa::card::Iter::new().flatten() {
+        let index = card.get_index() as u32;
+        if card_filter.is_some_and(|c| c != index) {
+            continue;
+        }
+        match read_card_controls(index) {
+            Ok(controls) => cards.push(controls),
+            Err(e) => eprintln!("Warning: Failed to read controls of card {}: {}", index, e),
+        }
+    }
+
+    cards
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_all_controls(_card_filter: Option<u32>) -> Vec<CardControls> {
+    Vec::new() // ALSA is Linux-specific
+}
+
+/// TLV type names (include/uapi/sound/tlv.h)
+fn tlv_type_name(tlv_type: u32) -> String {
+    match tlv_type {
+        0 => "CONTAINER".to_string(),
+        1 => "DB_SCALE".to_string(),
+        2 => "DB_LINEAR".to_string(),
+        3 => "DB_RANGE".to_string(),
+        4 => "DB_MINMAX".to_string(),
+        5 => "DB_MINMAX_MUTE".to_string(),
+        0x101 => "CHMAP_FIXED".to_string(),
+        0x102 => "CHMAP_VAR".to_string(),
+        0x103 => "CHMAP_PAIRED".to_string(),
+        other => format!("0x{:x}", other),
+    }
+}
+
+/// Largest TLV read from an element, in 32-bit words (16 KiB)
+#[cfg(target_os = "linux")]
+const MAX_TLV_WORDS: usize = 4096;
+
+/// alsa-lib reports a muted dB gain as this value (SND_CTL_TLV_DB_GAIN_MUTE)
+const DB_GAIN_MUTE: i64 = -9999999;
+
+/// A control handle and the scratch structures used to query it
+#[cfg(target_os = "linux")]
+pub(crate) struct RawCtl {
+    pub(crate) ctl: *mut alsa_sys::snd_ctl_t,
+    pub(crate) id: *mut alsa_sys::snd_ctl_elem_id_t,
+    pub(crate) info: *mut alsa_sys::snd_ctl_elem_info_t,
+    pub(crate) value: *mut alsa_sys::snd_ctl_elem_value_t,
+}
+
+#[cfg(target_os = "linux")]
+impl RawCtl {
+    pub(crate) fn open(card: u32) -> anyhow::Result<Self> {
+        use std::ffi::CString;
+        use std::ptr;
+
+        let name = CString::new(format!("hw:{}", card))?;
+        let mut raw = RawCtl {
+            ctl: ptr::null_mut(),
+            id: ptr::null_mut(),
+            info: ptr::null_mut(),
+            value: ptr::null_mut(),
+        };
+
+        // SAFETY: every pointer is either null or allocated here, and Drop
+        // releases whatever was allocated, including on early return
+        unsafe {
+            let err = alsa_sys::snd_ctl_open(&mut raw.ctl, name.as_ptr(), 0);
+            if err < 0 {
+                anyhow::bail!("cannot open control interface: {}", alsa::Error::new("snd_ctl_open", -err));
+            }
+            if alsa_sys::snd_ctl_elem_id_malloc(&mut raw.id) < 0
+                || alsa_sys::snd_ctl_elem_info_malloc(&mut raw.info) < 0
+                || alsa_sys::snd_ctl_elem_value_malloc(&mut raw.value) < 0
+            {
+                anyhow::bail!("out of memory");
+            }
+        }
+
+        Ok(raw)
+    }
+
+    /// IDs of every element on the card
+    pub(crate) fn element_ids(&self) -> Vec<u32> {
+        use std::ptr;
+
+        let mut numids = Vec::new();
+        // SAFETY: the list is allocated, sized and freed within this block
+        unsafe {
+            let mut list = ptr::null_mut();
+            if alsa_sys::snd_ctl_elem_list_malloc(&mut list) < 0 {
+                return numids;
+            }
+            // The first call only reports how many elements there are
+            if alsa_sys::snd_ctl_elem_list(self.ctl, list) >= 0 {
+                let count = alsa_sys::snd_ctl_elem_list_get_count(list);
+                if alsa_sys::snd_ctl_elem_list_alloc_space(list, count) >= 0 {
+                    if alsa_sys::snd_ctl_elem_list(self.ctl, list) >= 0 {
+                        for i in 0..alsa_sys::snd_ctl_elem_list_get_used(list) {
+                            numids.push(alsa_sys::snd_ctl_elem_list_get_numid(list, i));
+                        }
+                    }
+                    alsa_sys::snd_ctl_elem_list_free_space(list);
+                }
+            }
+            alsa_sys::snd_ctl_elem_list_free(list);
+        }
+        numids
+    }
+
+    /// Point the scratch id, info and value at element `numid` and load its info
+    pub(crate) fn select(&self, numid: u32) -> bool {
+        // SAFETY: all scratch structures were allocated in open()
+        unsafe {
+            alsa_sys::snd_ctl_elem_id_clear(self.id);
+            alsa_sys::snd_ctl_elem_id_set_numid(self.id, numid);
+            alsa_sys::snd_ctl_elem_info_set_id(self.info, self.id);
+            if alsa_sys::snd_ctl_elem_info(self.ctl, self.info) < 0 {
+                return false;
+            }
+            // Fill in name, interface and index from the kernel's answer
+            alsa_sys::snd_ctl_elem_info_get_id(self.info, self.id);
+            alsa_sys::snd_ctl_elem_value_set_id(self.value, self.id);
+        }
+        true
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for RawCtl {
+    fn drop(&mut self) {
+        // SAFETY: each pointer is null or owned by this struct
+        unsafe {
+            if !self.value.is_null() {
+                alsa_sys::snd_ctl_elem_value_free(self.value);
+            }
+            if !self.info.is_null() {
+                alsa_sys::snd_ctl_elem_info_free(self.info);
+            }
+            if !self.id.is_null() {
+                alsa_sys::snd_ctl_elem_id_free(self.id);
+            }
+            if !self.ctl.is_null() {
+                alsa_sys::snd_ctl_close(self.ctl);
+            }
+        }
+    }
+}
+
+/// Read every control element of one card
+#[cfg(target_os = "linux")]
+pub fn read_card_controls(card: u32) -> anyhow::Result<CardControls> {
+    let raw = RawCtl::open(card)?;
+    let id = alsa::Ctl::new(&format!("hw:{}", card), false)
+        .and_then(|ctl| ctl.card_info().and_then(|info| info.get_id().map(str::to_string)))
+        .unwrap_or_default();
+
+    let controls = raw.element_ids().into_iter()
+        .filter(|&numid| raw.select(numid))
+        .map(|numid| read_element(&raw, numid))
+        .collect();
+
+    Ok(CardControls { card, id, controls })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_card_controls(card: u32) -> anyhow::Result<CardControls> {
+    Ok(CardControls { card, id: String::new(), controls: Vec::new() }) // ALSA is Linux-specific
+}
+
+/// Describe the element currently selected in `raw`
+#[cfg(target_os = "linux")]
+fn read_element(raw: &RawCtl, numid: u32) -> ControlElement {
+    use alsa_sys::*;
+    use std::ffi::CStr;
+
+    let c_str = |ptr: *const std::ffi::c_char| if ptr.is_null() {
+        String::new()
+    } else {
+        // SAFETY: alsa-lib returns NUL-terminated strings owned by the queried object
+        unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+    };
+
+    let mut element = ControlElement { numid, ..Default::default() };
+
+    // SAFETY: raw.select() loaded the id and info of this element
+    unsafe {
+        element.interface = c_str(snd_ctl_elem_iface_name(snd_ctl_elem_id_get_interface(raw.id)));
+        element.name = c_str(snd_ctl_elem_id_get_name(raw.id));
+        element.index = snd_ctl_elem_id_get_index(raw.id);
+        element.device = snd_ctl_elem_id_get_device(raw.id);
+        element.subdevice = snd_ctl_elem_id_get_subdevice(raw.id);
+
+        let element_type = snd_ctl_elem_info_get_type(raw.info);
+        element.element_type = c_str(snd_ctl_elem_type_name(element_type));
+        element.count = snd_ctl_elem_info_get_count(raw.info);
+        element.access = ControlAccess {
+            readable: snd_ctl_elem_info_is_readable(raw.info) != 0,
+            writable: snd_ctl_elem_info_is_writable(raw.info) != 0,
+            volatile: snd_ctl_elem_info_is_volatile(raw.info) != 0,
+            inactive: snd_ctl_elem_info_is_inactive(raw.info) != 0,
+            locked: snd_ctl_elem_info_is_locked(raw.info) != 0,
+            user: snd_ctl_elem_info_is_user(raw.info) != 0,
+            tlv_readable: snd_ctl_elem_info_is_tlv_readable(raw.info) != 0,
+        };
+
+        match element_type {
+            SND_CTL_ELEM_TYPE_INTEGER => {
+                element.min = Some(snd_ctl_elem_info_get_min(raw.info) as i64);
+                element.max = Some(snd_ctl_elem_info_get_max(raw.info) as i64);
+                element.step = Some(snd_ctl_elem_info_get_step(raw.info) as i64);
+            }
+            SND_CTL_ELEM_TYPE_INTEGER64 => {
+                element.min = Some(snd_ctl_elem_info_get_min64(raw.info));
+                element.max = Some(snd_ctl_elem_info_get_max64(raw.info));
+                element.step = Some(snd_ctl_elem_info_get_step64(raw.info));
+            }
+            SND_CTL_ELEM_TYPE_ENUMERATED => {
+                // Each item name needs its own info query
+                for item in 0..snd_ctl_elem_info_get_items(raw.info) {
+                    snd_ctl_elem_info_set_item(raw.info, item);
+                    if snd_ctl_elem_info(raw.ctl, raw.info) >= 0 {
+                        element.enum_items.push(c_str(snd_ctl_elem_info_get_item_name(raw.info)));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if element.access.readable && snd_ctl_elem_read(raw.ctl, raw.value) >= 0 {
+            let count = element.count;
+            element.values = match element_type {
+                SND_CTL_ELEM_TYPE_BOOLEAN => Some(ControlValues::Boolean(
+                    (0..count).map(|i| snd_ctl_elem_value_get_boolean(raw.value, i) != 0).collect())),
+                SND_CTL_ELEM_TYPE_INTEGER => Some(ControlValues::Integer(
+                    (0..count).map(|i| snd_ctl_elem_value_get_integer(raw.value, i) as i64).collect())),
+                SND_CTL_ELEM_TYPE_INTEGER64 => Some(ControlValues::Integer(
+                    (0..count).map(|i| snd_ctl_elem_value_get_integer64(raw.value, i)).collect())),
+                SND_CTL_ELEM_TYPE_ENUMERATED => Some(ControlValues::Enumerated(
+                    (0..count).map(|i| {
+                        let item = snd_ctl_elem_value_get_enumerated(raw.value, i) as usize;
+                        element.enum_items.get(item).cloned().unwrap_or_else(|| item.to_string())
+                    }).collect())),
+                SND_CTL_ELEM_TYPE_BYTES => Some(ControlValues::Bytes(
+                    (0..count).map(|i| format!("{:02x}", snd_ctl_elem_value_get_byte(raw.value, i))).collect())),
+                _ => None,
+            };
+        }
+
+        if element.access.tlv_readable {
+            // DB_RANGE containers with many sub-ranges outgrow a small buffer;
+            // the kernel answers ENOMEM until the whole TLV fits
+            let mut words = 64;
+            while words <= MAX_TLV_WORDS {
+                let mut tlv = vec![0u32; words];
+                let err = snd_ctl_elem_tlv_read(raw.ctl, raw.id, tlv.as_mut_ptr(), (words * 4) as u32);
+                if err >= 0 {
+                    element.tlv_type = Some(tlv_type_name(tlv[0]));
+                    break;
+                }
+                if std::io::Error::from_raw_os_error(-err).kind() != std::io::ErrorKind::OutOfMemory {
+                    break;
+                }
+                words *= 4;
+            }
+            read_db(raw, &mut element);
+        }
+    }
+
+    element
+}
+
+/// Fill in the dB range and current values in dB of an integer element
+#[cfg(target_os = "linux")]
+fn read_db(raw: &RawCtl, element: &mut ControlElement) {
+    let (Some(min), Some(max)) = (element.min, element.max) else { return };
+
+    let to_db = |value: i64| -> Option<i64> {
+        let mut db = 0;
+        // SAFETY: the id was loaded by raw.select(); dB values are in 1/100 dB
+        let err = unsafe { alsa_sys::snd_ctl_convert_to_dB(raw.ctl, raw.id, value as _, &mut db) };
+        (err >= 0).then_some(db)
+    };
+
+    let mut min_db = 0;
+    let mut max_db = 0;
+    // SAFETY: as above
+    if unsafe { alsa_sys::snd_ctl_get_dB_range(raw.ctl, raw.id, &mut min_db, &mut max_db) } < 0 {
+        return;
+    }
+
+    // The range reports the scale's nominal minimum; whether that value mutes
+    // only shows when converting it, and the first audible step is more useful
+    let min_is_mute = to_db(min) == Some(DB_GAIN_MUTE);
+    let min_db = if min_is_mute && max > min { to_db(min + 1).unwrap_or(min_db) } else { min_db };
+    element.db_range = Some(DbRange {
+        min_db: min_db as f64 / 100.0,
+        max_db: max_db as f64 / 100.0,
+        min_is_mute,
+    });
+
+    if let Some(ControlValues::Integer(ref values)) = element.values {
+        element.values_db = values.iter()
+            .map(|&v| to_db(v).filter(|&db| db != DB_GAIN_MUTE).map(|db| db as f64 / 100.0))
+            .collect();
+    }
+}
+
+/// Write new values to elements of one card
+///
+/// Each entry pairs an element from the card's current inventory, which
+/// supplies its numid, type and enum items, with the values to write.
+/// Returns the elements that could not be written and why.
+#[cfg(target_os = "linux")]
+pub fn write_card_controls(card: u32, writes: &[(&ControlElement, &ControlValues)]) -> anyhow::Result<Vec<(String, String)>> {
+    use alsa_sys::*;
+
+    let raw = RawCtl::open(card)?;
+    let mut failures = Vec::new();
+
+    for &(element, values) in writes {
+        // alsa-lib asserts on out-of-range indices, so nothing unchecked reaches it
+        if let Err(reason) = element.check_values(values) {
+            failures.push((element.display_name(), reason));
+            continue;
+        }
+        if !raw.select(element.numid) {
+            failures.push((element.display_name(), "element no longer exists".to_string()));
+            continue;
+        }
+
+        // SAFETY: raw.select() pointed the scratch value at this element
+        let result = unsafe {
+            match (values, element.element_type.as_str()) {
+                (ControlValues::Boolean(v), _) => {
+                    for (i, &on) in v.iter().enumerate() {
+                        snd_ctl_elem_value_set_boolean(raw.value, i as u32, on as _);
+                    }
+                    Ok(())
+                }
+                (ControlValues::Integer(v), "INTEGER64") => {
+                    for (i, &n) in v.iter().enumerate() {
+                        snd_ctl_elem_value_set_integer64(raw.value, i as u32, n);
+                    }
+                    Ok(())
+                }
+                (ControlValues::Integer(v), _) => {
+                    for (i, &n) in v.iter().enumerate() {
+                        snd_ctl_elem_value_set_integer(raw.value, i as u32, n as _);
+                    }
+                    Ok(())
+                }
+                (ControlValues::Enumerated(v), _) => v.iter().enumerate().try_for_each(|(i, name)| {
+                    let item = element.enum_items.iter().position(|item| item == name)
+                        .ok_or_else(|| format!("no enum item '{}'", name))?;
+                    snd_ctl_elem_value_set_enumerated(raw.value, i as u32, item as u32);
+                    Ok(())
+                }),
+                (ControlValues::Bytes(hex), _) => decode_hex(hex).map(|bytes| {
+                    for (i, &byte) in bytes.iter().enumerate() {
+                        snd_ctl_elem_value_set_byte(raw.value, i as u32, byte);
+                    }
+                }),
+            }
+        };
+
+        let result = result.and_then(|()| {
+            // SAFETY: as above
+            let err = unsafe { snd_ctl_elem_write(raw.ctl, raw.value) };
+            if err < 0 { Err(alsa::Error::new("snd_ctl_elem_write", -err).to_string()) } else { Ok(()) }
+        });
+        if let Err(reason) = result {
+            failures.push((element.display_name(), reason));
+        }
+    }
+
+    Ok(failures)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn write_card_controls(_card: u32, _writes: &[(&ControlElement, &ControlValues)]) -> anyhow::Result<Vec<(String, String)>> {
+    anyhow::bail!("writing controls requires ALSA") // ALSA is Linux-specific
+}
+
+/// Decode the hex dump of a BYTES value
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let bytes = hex.as_bytes();
+    if !bytes.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
+    }
+    bytes.chunks(2)
+        .map(|pair| std::str::from_utf8(pair).ok()
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or_else(|| format!("invalid hex byte '{}'", String::from_utf8_lossy(pair))))
+        .collect()
+}
+
+impl ControlElement {
+    /// Check that `values` can be written to this element: the value type
+    /// matches the element type, there is one value per channel and every
+    /// enum item and hex byte is valid
+    pub fn check_values(&self, values: &ControlValues) -> Result<(), String> {
+        let len = match (values, self.element_type.as_str()) {
+            (ControlValues::Boolean(v), "BOOLEAN") => v.len(),
+            (ControlValues::Integer(v), "INTEGER" | "INTEGER64") => v.len(),
+            (ControlValues::Enumerated(v), "ENUMERATED") => {
+                if let Some(name) = v.iter().find(|name| !self.enum_items.contains(name)) {
+                    return Err(format!("no enum item '{}'", name));
+                }
+                v.len()
+            }
+            (ControlValues::Bytes(hex), "BYTES") => decode_hex(hex)?.len(),
+            (_, element_type) => return Err(format!("saved value does not fit a {} element", element_type)),
+        };
+        if len != self.count as usize {
+            return Err(format!("{} values saved, element has {}", len, self.count));
+        }
+        Ok(())
+    }
+
+    /// Name with index when there are several elements of the same name
+    pub fn display_name(&self) -> String {
+        if self.index > 0 {
+            format!("'{}',{}", self.name, self.index)
+        } else {
+            format!("'{}'", self.name)
+        }
+    }
+}
+
+/// Print one block per card, one line per element
+pub fn print_controls(cards: &[CardControls]) {
+    if cards.is_empty() {
+        println!("No sound cards with a control interface found.");
+        return;
+    }
+
+    for card in cards {
+        println!("┌─ Card {} ({}): {} controls", card.card, card.id, card.controls.len());
+
+        for (i, control) in card.controls.iter().enumerate() {
+            let branch = if i + 1 == card.controls.len() { "└─" } else { "├─" };
+            let mut line = format!("numid={} {} {} {} x{}",
+                control.numid, control.interface, control.display_name(), control.element_type, control.count);
+
+            if let (Some(min), Some(max)) = (control.min, control.max) {
+                line.push_str(&format!(" {}..{}", min, max));
+                if let Some(step) = control.step.filter(|&s| s > 1) {
+                    line.push_str(&format!(" step {}", step));
+                }
+            }
+            if !control.enum_items.is_empty() {
+                line.push_str(&format!(" {{{}}}", control.enum_items.join(", ")));
+            }
+            match control.values {
+                Some(ControlValues::Boolean(ref v)) => line.push_str(&format!(" = {:?}", v)),
+                Some(ControlValues::Integer(ref v)) => line.push_str(&format!(" = {:?}", v)),
+                Some(ControlValues::Enumerated(ref v)) => line.push_str(&format!(" = {:?}", v)),
+                Some(ControlValues::Bytes(ref hex)) => {
+                    let shown: String = hex.chars().take(32).collect();
+                    line.push_str(&format!(" = {}{}", shown, if hex.len() > 32 { "..." } else { "" }));
+                }
+                None => {}
+            }
+            if let Some(range) = control.db_range {
+                line.push_str(&format!(" ({:.2}..{:.2} dB{}", range.min_db, range.max_db,
+                    if range.min_is_mute { ", min mutes" } else { "" }));
+                if !control.values_db.is_empty() {
+                    let current: Vec<String> = control.values_db.iter()
+                        .map(|db| db.map_or("mute".to_string(), |db| format!("{:.2}", db)))
+                        .collect();
+                    line.push_str(&format!(", now {} dB", current.join("/")));
+                }
+                line.push(')');
+            }
+
+            let access = &control.access;
+            let flags: String = [
+                (access.readable, 'r'),
+                (access.writable, 'w'),
+                (access.volatile, 'v'),
+                (access.inactive, 'i'),
+                (access.locked, 'l'),
+                (access.user, 'u'),
+            ].iter().map(|&(set, flag)| if set { flag } else { '-' }).collect();
+            line.push_str(&format!(" [{}]", flags));
+
+            println!("{} {}", branch, line);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_values_round_trip_untagged() {
+        for values in [
+            ControlValues::Boolean(vec![true, false]),
+            ControlValues::Integer(vec![87, -3]),
+            ControlValues::Enumerated(vec!["Mic".to_string()]),
+            ControlValues::Bytes("0a1b".to_string()),
+        ] {
+            let json = serde_json::to_string(&values).unwrap();
+            assert_eq!(serde_json::from_str::<ControlValues>(&json).unwrap(), values);
+        }
+        assert_eq!(tlv_type_name(1), "DB_SCALE");
+        assert_eq!(decode_hex("0aFF"), Ok(vec![0x0a, 0xff]));
+        assert!(decode_hex("0a1").is_err());
+        assert!(decode_hex("zz").is_err());
+        // Multi-byte characters must not be sliced through
+        assert!(decode_hex("é0").is_err());
+    }
+
+    #[test]
+    fn checks_values_against_element() {
+        let element = ControlElement {
+            element_type: "ENUMERATED".to_string(),
+            count: 1,
+            enum_items: vec!["Line".to_string(), "Mic".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(element.check_values(&ControlValues::Enumerated(vec!["Mic".to_string()])), Ok(()));
+        assert!(element.check_values(&ControlValues::Enumerated(vec!["Inst".to_string()])).is_err());
+        assert!(element.check_values(&ControlValues::Integer(vec![1])).is_err());
+
+        let bytes = ControlElement { element_type: "BYTES".to_string(), count: 2, ..Default::default() };
+        assert_eq!(bytes.check_values(&ControlValues::Bytes("0a1b".to_string())), Ok(()));
+        assert_eq!(bytes.check_values(&ControlValues::Bytes("0a".repeat(600))),
+            Err("600 values saved, element has 2".to_string()));
+        assert_eq!(tlv_type_name(0x102), "CHMAP_VAR");
+    }
+}
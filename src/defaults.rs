@@ -0,0 +1,311 @@
+//! Default input/output device resolution
+//!
+//! The default device is resolved from the mechanisms applications actually
+//! use, in order of precedence: a running sound server's default sink and
+//! source, the ALSA `pcm.default` definition (or the `defaults.pcm.card` and
+//! `defaults.pcm.device` it falls back to), and finally CPAL's default host
+//! devices.
+
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Which mechanism identified a default device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultSource {
+    /// Default sink/source of a PulseAudio or PipeWire server
+    SoundServer,
+    /// `pcm.default` or `defaults.pcm.card` from the ALSA configuration
+    AlsaConfig,
+    /// CPAL's `default_input_device`/`default_output_device`
+    Cpal,
+}
+
+impl DefaultSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DefaultSource::SoundServer => "sound server",
+            DefaultSource::AlsaConfig => "ALSA configuration",
+            DefaultSource::Cpal => "CPAL",
+        }
+    }
+}
+
+/// A resolved default device and where the answer came from
+#[derive(Debug, Clone)]
+pub struct DefaultDevice {
+    pub name: String,
+    pub source: DefaultSource,
+}
+
+/// Resolve the default (input, output) devices
+pub fn resolve_default_devices() -> (Option<DefaultDevice>, Option<DefaultDevice>) {
+    let (server_input, server_output) = sound_server_defaults();
+    let (alsa_input, alsa_output) = alsa_config_defaults();
+
+    let input = server_input
+        .map(|name| DefaultDevice { name, source: DefaultSource::SoundServer })
+        .or_else(|| alsa_input.map(|name| DefaultDevice { name, source: DefaultSource::AlsaConfig }))
+        .or_else(|| cpal_default(true).map(|name| DefaultDevice { name, source: DefaultSource::Cpal }));
+
+    let output = server_output
+        .map(|name| DefaultDevice { name, source: DefaultSource::SoundServer })
+        .or_else(|| alsa_output.map(|name| DefaultDevice { name, source: DefaultSource::AlsaConfig }))
+        .or_else(|| cpal_default(false).map(|name| DefaultDevice { name, source: DefaultSource::Cpal }));
+
+    (input, output)
+}
+
+/// Ask a PulseAudio-compatible server (including pipewire-pulse) for its defaults
+///
+/// `pactl` exits with an error when no server is running, which is treated as
+/// "no sound server configured".
+fn sound_server_defaults() -> (Option<String>, Option<String>) {
+    let output = match Command::new("pactl").arg("info").output() {
+        Ok(output) if output.status.success() => output,
+        _ => return (None, None),
+    };
+
+    parse_pactl_info(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extract "Default Source:" and "Default Sink:" from `pactl info` output
+fn parse_pactl_info(info: &str) -> (Option<String>, Option<String>) {
+    let mut source = None;
+    let mut sink = None;
+
+    for line in info.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            if value.is_empty() || value == "@DEFAULT_SINK@" || value == "@DEFAULT_SOURCE@" {
+                continue;
+            }
+            match key.trim() {
+                "Default Source" => source = Some(value.to_string()),
+                "Default Sink" => sink = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    (source, sink)
+}
+
+/// PCM definitions followed before giving up, which also stops reference cycles
+#[cfg(target_os = "linux")]
+const MAX_PCM_DEPTH: usize = 8;
+
+/// Resolve the card and device the ALSA `default` PCM points at
+///
+/// A `pcm.!default` from asoundrc is followed through `hw`, plug-style
+/// (`slave.pcm`) and `asym` definitions and the PCMs they name down to a
+/// hardware device. The stock
+/// definition is an alias for the card's own default, which mirrors alsa.conf:
+/// `ALSA_PCM_CARD`/`ALSA_CARD` override `defaults.pcm.card`, and
+/// `ALSA_PCM_DEVICE` overrides `defaults.pcm.device`. The card may be given by
+/// index or by its id string.
+#[cfg(target_os = "linux")]
+fn alsa_config_defaults() -> (Option<String>, Option<String>) {
+    let Some(alias) = alsa_config_value("pcm.default") else {
+        return alsa_pcm_definition("pcm.default", 1);
+    };
+    if !alias.starts_with("cards.") {
+        return alsa_pcm_devices(&alias, 1);
+    }
+
+    // Stock alsa.conf: `pcm.default cards.pcm.default`
+    let env_card = std::env::var("ALSA_PCM_CARD").or_else(|_| std::env::var("ALSA_CARD")).ok();
+    let env_device = std::env::var("ALSA_PCM_DEVICE").ok();
+    let configured = env_card.is_some() || env_device.is_some();
+    let card = env_card.or_else(|| alsa_config_value("defaults.pcm.card")).unwrap_or_else(|| "0".to_string());
+    let device = env_device.or_else(|| alsa_config_value("defaults.pcm.device"));
+
+    // alsa.conf itself sets card 0 device 0, so that pair says nothing about
+    // what the user chose; CPAL knows better than a guess at card 0
+    if !configured && card == "0" && device.as_deref().unwrap_or("0") == "0" {
+        return (None, None);
+    }
+
+    let name = hw_device_name(&card, device);
+    (name.clone(), name)
+}
+
+/// Follow the PCM named `pcm` ("hw:1,0", "mydev") to (capture, playback) hardware devices
+#[cfg(target_os = "linux")]
+fn alsa_pcm_devices(pcm: &str, depth: usize) -> (Option<String>, Option<String>) {
+    if let Some((card, device)) = parse_hw_pcm(pcm) {
+        let name = hw_device_name(&card, device);
+        return (name.clone(), name);
+    }
+    // Plugins taking arguments ("dmix:0") expand templates that are not followed
+    if pcm.contains(':') || depth >= MAX_PCM_DEPTH {
+        return (None, None);
+    }
+
+    let key = format!("pcm.{}", pcm);
+    match alsa_config_value(&key) {
+        Some(alias) => alsa_pcm_devices(&alias, depth + 1),
+        None => alsa_pcm_definition(&key, depth + 1),
+    }
+}
+
+/// Follow the compound PCM definition at `key` to (capture, playback) hardware devices
+#[cfg(target_os = "linux")]
+fn alsa_pcm_definition(key: &str, depth: usize) -> (Option<String>, Option<String>) {
+    if depth >= MAX_PCM_DEPTH {
+        return (None, None);
+    }
+    // A slave is either the name of another PCM or an inline definition
+    let slave = |key: String| match alsa_config_value(&key) {
+        Some(pcm) => alsa_pcm_devices(&pcm, depth + 1),
+        None => alsa_pcm_definition(&key, depth + 1),
+    };
+
+    match alsa_config_value(&format!("{}.type", key)).as_deref() {
+        Some("hw") => {
+            let card = alsa_config_value(&format!("{}.card", key)).unwrap_or_else(|| "0".to_string());
+            let name = hw_device_name(&card, alsa_config_value(&format!("{}.device", key)));
+            (name.clone(), name)
+        }
+        Some("asym") => (
+            slave(format!("{}.capture.pcm", key)).0,
+            slave(format!("{}.playback.pcm", key)).1,
+        ),
+        // plug, route, softvol and friends wrap a single slave
+        Some(_) => slave(format!("{}.slave.pcm", key)),
+        None => (None, None),
+    }
+}
+
+/// Split a `hw:`/`plughw:` PCM name into its card and device arguments
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_hw_pcm(pcm: &str) -> Option<(String, Option<String>)> {
+    let (plugin, args) = pcm.split_once(':')?;
+    if plugin != "hw" && plugin != "plughw" {
+        return None;
+    }
+
+    let mut card = None;
+    let mut device = None;
+    for (i, arg) in args.split(',').enumerate() {
+        match arg.split_once('=') {
+            Some(("CARD", value)) => card = Some(value),
+            Some(("DEV", value)) => device = Some(value),
+            Some(_) => {}
+            None if i == 0 => card = Some(arg),
+            None if i == 1 => device = Some(arg),
+            None => {}
+        }
+    }
+
+    Some((card?.trim_matches('"').to_string(), device.map(|d| d.trim_matches('"').to_string())))
+}
+
+/// "hw:N,M" for a card given by index or id, if the card is present
+#[cfg(target_os = "linux")]
+fn hw_device_name(card: &str, device: Option<String>) -> Option<String> {
+    use std::ffi::CString;
+
+    // Card ids ("PCH", "USB") resolve through alsa-lib; indices pass through
+    let card_index = match card.parse::<i32>() {
+        Ok(index) => index,
+        Err(_) => CString::new(card).ok().and_then(|id| alsa::Card::from_str(&id).ok())?.get_index(),
+    };
+
+    // A configured card that isn't present means the default PCM cannot open
+    alsa::Ctl::new(&format!("hw:{}", card_index), false).ok()?;
+
+    let device = device.and_then(|d| d.parse::<i32>().ok()).unwrap_or(0);
+    Some(format!("hw:{},{}", card_index, device))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn alsa_config_defaults() -> (Option<String>, Option<String>) {
+    (None, None) // ALSA is Linux-specific
+}
+
+/// Read a scalar (integer or string) from the merged ALSA configuration tree
+#[cfg(target_os = "linux")]
+fn alsa_config_value(key: &str) -> Option<String> {
+    use std::ffi::{c_char, c_long, CStr, CString};
+    use std::ptr;
+
+    let key = CString::new(key).ok()?;
+    let mut top = ptr::null_mut();
+
+    // SAFETY: snd_config_update_ref hands out a reference-counted tree that is
+    // released with snd_config_unref below; nodes are only read while it is held.
+    unsafe {
+        if alsa_sys::snd_config_update_ref(&mut top) < 0 || top.is_null() {
+            return None;
+        }
+
+        let mut node = ptr::null_mut();
+        let value = if alsa_sys::snd_config_search(top, key.as_ptr(), &mut node) < 0 {
+            None
+        } else {
+            match alsa_sys::snd_config_get_type(node) {
+                alsa_sys::SND_CONFIG_TYPE_INTEGER => {
+                    let mut value: c_long = 0;
+                    (alsa_sys::snd_config_get_integer(node, &mut value) >= 0).then(|| value.to_string())
+                }
+                alsa_sys::SND_CONFIG_TYPE_STRING => {
+                    let mut value: *const c_char = ptr::null();
+                    if alsa_sys::snd_config_get_string(node, &mut value) >= 0 && !value.is_null() {
+                        Some(CStr::from_ptr(value).to_string_lossy().into_owned())
+                    } else {
+                        None
+                    }
+                }
+                // Compound nodes (e.g. @func getenv) are resolved at PCM open time
+                _ => None,
+            }
+        };
+
+        alsa_sys::snd_config_unref(top);
+        value
+    }
+}
+
+/// Name of CPAL's default input or output device on the default host
+fn cpal_default(input: bool) -> Option<String> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    let host = cpal::default_host();
+    let device = if input { host.default_input_device() } else { host.default_output_device() };
+    device.and_then(|d| d.name().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pactl_info() {
+        let info = "Server String: /run/user/1000/pulse/native
+Server Name: PulseAudio (on PipeWire 1.0.5)
+Default Sink: alsa_output.pci-0000_00_1f.3.analog-stereo
+Default Source: alsa_input.usb-Focusrite_Scarlett_2i2_USB-00.analog-stereo
+Cookie: 6a1f:2c3b
+";
+        assert_eq!(parse_pactl_info(info), (
+            Some("alsa_input.usb-Focusrite_Scarlett_2i2_USB-00.analog-stereo".to_string()),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo".to_string()),
+        ));
+
+        // A server without devices reports its placeholders
+        let idle = "Default Sink: @DEFAULT_SINK@\nDefault Source: \n";
+        assert_eq!(parse_pactl_info(idle), (None, None));
+    }
+
+    #[test]
+    fn parses_hw_pcm_names() {
+        let owned = |card: &str, device: Option<&str>| Some((card.to_string(), device.map(str::to_string)));
+        assert_eq!(parse_hw_pcm("hw:1,0"), owned("1", Some("0")));
+        assert_eq!(parse_hw_pcm("plughw:USB"), owned("USB", None));
+        assert_eq!(parse_hw_pcm("hw:CARD=PCH,DEV=3"), owned("PCH", Some("3")));
+        assert_eq!(parse_hw_pcm("plughw:DEV=1,CARD=\"Generic\""), owned("Generic", Some("1")));
+        assert_eq!(parse_hw_pcm("dmix:0"), None);
+        assert_eq!(parse_hw_pcm("pulse"), None);
+    }
+}
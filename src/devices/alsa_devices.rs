@@ -0,0 +1,625 @@
+//! ALSA backend
+//!
+//! Every PCM the ALSA configuration defines (the name hints `aplay -L`
+//! prints) plus every hardware PCM on every card, each opened and probed
+//! for its full hardware parameter space. `/proc/asound` fills in USB
+//! stream descriptions, live subdevice state and PCMs too busy to open.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Result};
+
+use crate::sysroot::SysRoot;
+use crate::{chmap, jacks, pcm_status, usb_stream};
+use super::backend::{DeviceBackend, ProbeOptions};
+use super::cards::{card_index_from_name, get_card_mapping, pcm_index_from_name};
+use super::{buffer_sizes_for_range, default_size_in_range, AudioDeviceInfo, HwCapabilities, StreamCapabilities};
+
+/// Configured and hardware PCMs, opened and probed through alsa-lib
+pub struct AlsaBackend;
+
+/// PCMs listed under `/proc/asound`, read without opening them
+///
+/// This still finds PCMs that another application holds open, which the
+/// ALSA backend cannot probe; devices it did probe take precedence.
+pub struct ProcBackend;
+
+impl DeviceBackend for AlsaBackend {
+    fn name(&self) -> &str {
+        "alsa"
+    }
+
+    fn check_available(&self, options: &ProbeOptions) -> Result<()> {
+        if cfg!(not(target_os = "linux")) {
+            bail!("ALSA is Linux-specific");
+        }
+        if !options.sysroot.is_host() {
+            bail!("probes the running system, not a sysroot");
+        }
+        Ok(())
+    }
+
+    fn enumerate(&self, options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+        super::get_alsa_devices(options)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn probe(&self, name: &str, options: &ProbeOptions) -> Result<Option<AudioDeviceInfo>> {
+        // Hardware names also get their /proc details, like enumerated ones
+        let hw_pcm = match name.strip_prefix("hw:") {
+            Some(_) => card_index_from_name(name, &get_card_mapping(&options.sysroot).unwrap_or_default())
+                .zip(pcm_index_from_name(name))
+                .map(|(card, pcm)| (card as i32, pcm as i32)),
+            None => None,
+        };
+        let target = AlsaProbeTarget {
+            name: name.to_string(),
+            description: None,
+            playback: true,
+            capture: true,
+            from_hint: false,
+            hw_pcm,
+        };
+
+        let mut devices: Vec<AudioDeviceInfo> = probe_target(target, proc_asound(options).as_deref()).into_iter().collect();
+        attach_jacks(&mut devices, &options.sysroot);
+        Ok(devices.pop())
+    }
+}
+
+impl DeviceBackend for ProcBackend {
+    fn name(&self) -> &str {
+        "proc"
+    }
+
+    fn check_available(&self, options: &ProbeOptions) -> Result<()> {
+        if options.no_proc {
+            bail!("/proc/asound access is disabled");
+        }
+        if !options.sysroot.asound().is_dir() {
+            bail!("{} not found", options.sysroot.asound().display());
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn enumerate(&self, options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+        let mut devices: Vec<AudioDeviceInfo> = Vec::new();
+
+        // The playback and capture halves of each PCM are merged into one device
+        for proc_device in get_proc_alsa_devices_safe(&options.sysroot)? {
+            match devices.iter_mut().find(|d| d.name == proc_device.name) {
+                Some(existing) => {
+                    existing.playback = existing.playback.take().or(proc_device.playback);
+                    existing.capture = existing.capture.take().or(proc_device.capture);
+                    existing.subdevices.extend(proc_device.subdevices);
+                    existing.in_use |= proc_device.in_use;
+                    existing.update_from_streams();
+                }
+                None => devices.push(proc_device),
+            }
+        }
+
+        // Jack states come from the controls of the running system
+        if options.sysroot.is_host() {
+            attach_jacks(&mut devices, &options.sysroot);
+        }
+        Ok(devices)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn enumerate(&self, _options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+        Ok(Vec::new()) // ALSA is Linux-specific
+    }
+}
+
+/// `/proc/asound` below the sysroot, unless reading it is disabled
+fn proc_asound(options: &ProbeOptions) -> Option<PathBuf> {
+    (!options.no_proc).then(|| options.sysroot.asound())
+}
+
+/// Probe every configured and hardware PCM
+///
+/// With `no_proc` unset, hardware PCMs also get their USB stream description
+/// and live subdevice state from `/proc/asound`.
+#[cfg(target_os = "linux")]
+pub fn get_alsa_devices(options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+    let mut targets: Vec<AlsaProbeTarget> = Vec::new();
+
+    // Every PCM the ALSA configuration defines, including plugin and virtual devices
+    for hint in get_alsa_pcm_hints() {
+        targets.push(AlsaProbeTarget {
+            name: hint.name,
+            description: hint.description,
+            playback: hint.playback,
+            capture: hint.capture,
+            from_hint: true,
+            hw_pcm: None,
+        });
+    }
+
+    // The default PCM is not tied to a card, so probe it explicitly if the hints missed it
+    if !targets.iter().any(|t| t.name == "default") {
+        targets.insert(0, AlsaProbeTarget {
+            name: "default".to_string(),
+            description: None,
+            playback: true,
+            capture: true,
+            from_hint: false,
+            hw_pcm: None,
+        });
+    }
+
+    // Walk every card and PCM device the kernel exposes
+    for pcm in enumerate_alsa_pcms()? {
+        targets.push(AlsaProbeTarget {
+            name: format!("hw:{},{}", pcm.card, pcm.device),
+            description: None,
+            playback: pcm.playback_subdevices > 0,
+            capture: pcm.capture_subdevices > 0,
+            from_hint: false,
+            hw_pcm: Some((pcm.card, pcm.device)),
+        });
+    }
+
+    let mut devices: Vec<AudioDeviceInfo> = targets.into_iter()
+        .filter_map(|target| probe_target(target, proc_asound(options).as_deref()))
+        .collect();
+    attach_jacks(&mut devices, &options.sysroot);
+
+    Ok(devices)
+}
+
+/// Open one target in each direction it supports and build its device
+#[cfg(target_os = "linux")]
+fn probe_target(target: AlsaProbeTarget, asound: Option<&Path>) -> Option<AudioDeviceInfo> {
+    let AlsaProbeTarget {
+        name: device_name,
+        description,
+        playback: has_playback,
+        capture: has_capture,
+        from_hint,
+        hw_pcm,
+    } = target;
+
+    let playback_hw = if has_playback {
+        probe_alsa_pcm(&device_name, alsa::Direction::Playback)
+    } else {
+        None
+    };
+    let capture_hw = if has_capture {
+        probe_alsa_pcm(&device_name, alsa::Direction::Capture)
+    } else {
+        None
+    };
+
+    // Buffer and period sizes come from playback when available, like the rates used to
+    let hw = playback_hw.as_ref().or(capture_hw.as_ref());
+    let frame_range = |min: Option<u64>, max: Option<u64>| -> Option<(u32, u32)> {
+        Some((clamp_to_u32(min?), clamp_to_u32(max?)))
+    };
+    let buffer_range = hw.and_then(|hw| frame_range(hw.min_buffer_size, hw.max_buffer_size));
+    let period_range = hw.and_then(|hw| frame_range(hw.min_period_size, hw.max_period_size));
+
+    let mut device = AudioDeviceInfo::new(device_name, "ALSA".to_string());
+    device.description = description;
+    (device.supported_buffer_sizes, device.buffer_size_source) = buffer_sizes_for_range(buffer_range);
+    if period_range.is_some() {
+        (device.supported_period_sizes, device.period_size_source) = buffer_sizes_for_range(period_range);
+    }
+    device.default_buffer_size = default_size_in_range(buffer_range);
+    device.playback = playback_hw.map(StreamCapabilities::from_hw);
+    device.capture = capture_hw.map(StreamCapabilities::from_hw);
+    device.update_from_streams();
+
+    // USB interfaces describe every altset in /proc without opening the device,
+    // and each subdevice reports what an application negotiated on it
+    if let (Some(asound), Some((card, pcm))) = (asound, hw_pcm) {
+        let card_path = asound.join(format!("card{}", card));
+        device.usb_stream = usb_stream::read_usb_stream(&card_path, pcm as u32);
+        device.subdevices = pcm_status::read_pcm_subdevices(&card_path, pcm as u32);
+        device.in_use = device.subdevices.iter().any(|s| s.in_use);
+    }
+
+    // Only keep the device if it has input or output capabilities, or if the
+    // ALSA configuration lists it (plugins may refuse to open while probing)
+    if device.has_input() || device.has_output() {
+        return Some(device);
+    }
+    if !from_hint {
+        return None;
+    }
+
+    // Fall back to the direction advertised by the hint's IOID
+    device.device_type = match (has_capture, has_playback) {
+        (true, true) => "Input/Output".to_string(),
+        (true, false) => "Input".to_string(),
+        (false, true) => "Output".to_string(),
+        (false, false) => return None,
+    };
+    Some(device)
+}
+
+/// Attach "... Jack" control states to the hardware PCMs they belong to
+#[cfg(target_os = "linux")]
+fn attach_jacks(devices: &mut [AudioDeviceInfo], root: &SysRoot) {
+    let card_mapping = get_card_mapping(root).unwrap_or_default();
+    let mut card_jacks: HashMap<u32, Vec<jacks::JackState>> = HashMap::new();
+
+    for device in devices.iter_mut() {
+        if !(device.name.starts_with("hw:") || device.name.starts_with("plughw:")) {
+            continue;
+        }
+        let (Some(card), Some(pcm)) = (card_index_from_name(&device.name, &card_mapping), pcm_index_from_name(&device.name)) else { continue };
+        let available = card_jacks.entry(card)
+            .or_insert_with(|| jacks::read_card_jacks(card).unwrap_or_default());
+
+        let (has_playback, has_capture) = (device.playback.is_some(), device.capture.is_some());
+        device.jacks = available.iter()
+            .filter(|jack| jack.pcm_device == Some(pcm))
+            .filter(|jack| match jack.direction.as_deref() {
+                Some("playback") => has_playback,
+                Some("capture") => has_capture,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+    }
+}
+
+/// A PCM name to open and probe, with what is known about it up front
+#[cfg(target_os = "linux")]
+struct AlsaProbeTarget {
+    name: String,
+    description: Option<String>,
+    playback: bool,
+    capture: bool,
+    /// Listed by the ALSA name hints (kept even if it fails to open)
+    from_hint: bool,
+    /// (card, device) for hardware PCMs found through the control interface
+    hw_pcm: Option<(i32, i32)>,
+}
+
+/// A PCM listed by the ALSA name hints (what `aplay -L` prints)
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct AlsaPcmHint {
+    name: String,
+    description: Option<String>,
+    playback: bool,
+    capture: bool,
+}
+
+/// Read the ALSA name hints (NAME, DESC, IOID) for every configured PCM
+#[cfg(target_os = "linux")]
+fn get_alsa_pcm_hints() -> Vec<AlsaPcmHint> {
+    use alsa::device_name::HintIter;
+    use alsa::Direction;
+
+    let hints = match HintIter::new_str(None, "pcm") {
+        Ok(hints) => hints,
+        Err(e) => {
+            eprintln!("Warning: Failed to read ALSA name hints: {}", e);
+            return Vec::new();
+        }
+    };
+
+    hints
+        .filter_map(|hint| {
+            let name = hint.name?;
+            // Descriptions are split over several lines; keep them on one
+            let description = hint.desc.map(|desc| {
+                desc.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+            // A missing IOID means the PCM works in both directions
+            let (playback, capture) = match hint.direction {
+                Some(Direction::Playback) => (true, false),
+                Some(Direction::Capture) => (false, true),
+                None => (true, true),
+            };
+            Some(AlsaPcmHint { name, description, playback, capture })
+        })
+        .collect()
+}
+
+/// A PCM device found through the ALSA control interface
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct AlsaPcmRef {
+    card: i32,
+    device: i32,
+    playback_subdevices: u32,
+    capture_subdevices: u32,
+}
+
+/// Enumerate every PCM device on every card via the control interface
+#[cfg(target_os = "linux")]
+fn enumerate_alsa_pcms() -> Result<Vec<AlsaPcmRef>> {
+    use alsa::ctl::{Ctl, DeviceIter};
+    use alsa::Direction;
+
+    let mut pcms = Vec::new();
+
+    for card in alsa::card::Iter::new() {
+        let card = match card {
+            Ok(card) => card,
+            Err(e) => {
+                eprintln!("Warning: Failed to enumerate ALSA card: {}", e);
+                continue;
+            }
+        };
+
+        let ctl = match Ctl::from_card(&card, false) {
+            Ok(ctl) => ctl,
+            Err(e) => {
+                eprintln!("Warning: Failed to open control for card {}: {}", card.get_index(), e);
+                continue;
+            }
+        };
+
+        for device in DeviceIter::new(&ctl) {
+            // Walk the subdevices in each direction; a device may only exist in one
+            let count_subdevices = |direction: Direction| -> u32 {
+                let total = match ctl.pcm_info(device as u32, 0, direction) {
+                    Ok(info) => info.get_subdevices_count(),
+                    Err(_) => return 0,
+                };
+                (0..total)
+                    .filter(|&sub| ctl.pcm_info(device as u32, sub, direction).is_ok())
+                    .count() as u32
+            };
+
+            let playback_subdevices = count_subdevices(Direction::Playback);
+            let capture_subdevices = count_subdevices(Direction::Capture);
+
+            if playback_subdevices > 0 || capture_subdevices > 0 {
+                pcms.push(AlsaPcmRef {
+                    card: card.get_index(),
+                    device,
+                    playback_subdevices,
+                    capture_subdevices,
+                });
+            }
+        }
+    }
+
+    Ok(pcms)
+}
+
+/// Open a PCM in one direction and read its full hardware parameter space
+///
+/// Nothing is restricted before reading, so devices that only accept e.g.
+/// S32_LE or S24_3LE report their real channel counts.
+#[cfg(target_os = "linux")]
+fn probe_alsa_pcm(device_name: &str, direction: alsa::Direction) -> Option<HwCapabilities> {
+    use alsa::{Output, PCM};
+    use alsa::pcm::{Access, Format, HwParams};
+
+    let pcm = PCM::new(device_name, direction, false).ok()?;
+    let hwp = HwParams::any(&pcm).ok()?;
+
+    // Format::Unknown is -1 and trips an assertion inside alsa-lib's mask code
+    let formats = Format::all()
+        .iter()
+        .filter(|&&format| format != Format::Unknown && hwp.test_format(format).is_ok())
+        .map(|format| format.to_string())
+        .collect();
+
+    // HwParams has no test_access, so restrict a copy and throw it away
+    let access_types = Access::all()
+        .iter()
+        .filter(|&&access| hwp.clone().set_access(access).is_ok())
+        .map(|&access| access_name(access).to_string())
+        .collect();
+
+    let min_rate = hwp.get_rate_min().unwrap_or(0);
+    let max_rate = hwp.get_rate_max().unwrap_or(0);
+
+    // Add common sample rates the device accepts
+    let common_rates = [8000, 11025, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000, 352800, 384000];
+    let mut sample_rates: Vec<u32> = common_rates
+        .iter()
+        .copied()
+        .filter(|&rate| hwp.test_rate(rate).is_ok())
+        .collect();
+    if sample_rates.is_empty() && max_rate > 0 {
+        sample_rates.push(min_rate);
+        sample_rates.push(max_rate);
+        sample_rates.dedup();
+    }
+
+    // Period counts and buffer bytes have no getters; read them from the dump
+    let mut periods = None;
+    let mut buffer_bytes = None;
+    if let Ok(mut output) = Output::buffer_open() {
+        if hwp.dump(&mut output).is_ok() {
+            let dump = output.to_string();
+            periods = parse_hw_params_dump_interval(&dump, "PERIODS");
+            buffer_bytes = parse_hw_params_dump_interval(&dump, "BUFFER_BYTES");
+        }
+    }
+
+    Some(HwCapabilities {
+        formats,
+        access_types,
+        min_channels: hwp.get_channels_min().unwrap_or(0),
+        max_channels: hwp.get_channels_max().unwrap_or(0),
+        min_rate,
+        max_rate,
+        sample_rates,
+        min_period_size: hwp.get_period_size_min().ok().map(|v| v as u64),
+        max_period_size: hwp.get_period_size_max().ok().map(|v| v as u64),
+        min_buffer_size: hwp.get_buffer_size_min().ok().map(|v| v as u64),
+        max_buffer_size: hwp.get_buffer_size_max().ok().map(|v| v as u64),
+        min_periods: periods.map(|(min, _)| min as u32),
+        max_periods: periods.map(|(_, max)| max as u32),
+        min_buffer_bytes: buffer_bytes.map(|(min, _)| min),
+        max_buffer_bytes: buffer_bytes.map(|(_, max)| max),
+        channel_maps: chmap::read_channel_maps(&pcm),
+    })
+}
+
+fn clamp_to_u32(value: u64) -> u32 {
+    value.min(u32::MAX as u64) as u32
+}
+
+#[cfg(target_os = "linux")]
+fn access_name(access: alsa::pcm::Access) -> &'static str {
+    use alsa::pcm::Access;
+
+    match access {
+        Access::MMapInterleaved => "MMAP_INTERLEAVED",
+        Access::MMapNonInterleaved => "MMAP_NONINTERLEAVED",
+        Access::MMapComplex => "MMAP_COMPLEX",
+        Access::RWInterleaved => "RW_INTERLEAVED",
+        Access::RWNonInterleaved => "RW_NONINTERLEAVED",
+    }
+}
+
+/// Parse one interval line of `snd_pcm_hw_params_dump` output
+///
+/// Lines look like "PERIODS: [2 32]", "BUFFER_BYTES: (64 65536]" or
+/// "CHANNELS: 2"; open bounds are narrowed to the nearest integer inside.
+fn parse_hw_params_dump_interval(dump: &str, key: &str) -> Option<(u64, u64)> {
+    let value = dump.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim())
+    })?;
+
+    if let Ok(single) = value.parse::<u64>() {
+        return Some((single, single));
+    }
+
+    let open_min = value.starts_with('(');
+    let open_max = value.ends_with(')');
+    let inner = value.trim_start_matches(['[', '(']).trim_end_matches([']', ')']);
+    let mut bounds = inner.split_whitespace().map(|v| v.parse::<u64>());
+    let (Some(Ok(mut min)), Some(Ok(mut max))) = (bounds.next(), bounds.next()) else {
+        return None;
+    };
+
+    if open_min {
+        min += 1;
+    }
+    if open_max {
+        max = max.saturating_sub(1);
+    }
+
+    Some((min, max))
+}
+
+#[cfg(target_os = "linux")]
+fn get_proc_alsa_devices_safe(root: &SysRoot) -> Result<Vec<AudioDeviceInfo>> {
+    use std::fs;
+
+    let mut devices = Vec::new();
+
+    // Check /proc/asound/ for card directories
+    if let Ok(entries) = fs::read_dir(root.asound()) {
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if let Some(name_str) = name.to_str() {
+                if let Some(card_num) = name_str.strip_prefix("card") {
+                    let card_path = entry.path();
+
+                    // Check for PCM devices
+                    if let Ok(card_entries) = fs::read_dir(&card_path) {
+                        for card_entry in card_entries.flatten() {
+                            let pcm_name = card_entry.file_name();
+                            if let Some(pcm_str) = pcm_name.to_str() {
+                                // Check for playback devices (pcmXp)
+                                if pcm_str.starts_with("pcm") && pcm_str.ends_with("p") {
+                                    if let Some(device_info) = read_pcm_info_safe(&card_path, pcm_str, "PLAYBACK", card_num) {
+                                        devices.push(device_info);
+                                    }
+                                }
+                                // Check for capture devices (pcmXc)
+                                if pcm_str.starts_with("pcm") && pcm_str.ends_with("c") {
+                                    if let Some(device_info) = read_pcm_info_safe(&card_path, pcm_str, "CAPTURE", card_num) {
+                                        devices.push(device_info);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(devices)
+}
+
+#[cfg(target_os = "linux")]
+fn read_pcm_info_safe(card_path: &Path, pcm_dir: &str, stream_type: &str, card_num: &str) -> Option<AudioDeviceInfo> {
+    use std::fs;
+
+    let info_path = card_path.join(pcm_dir).join("info");
+
+    if let Ok(info_content) = fs::read_to_string(&info_path) {
+        // Parse device number from pcmXp or pcmXc
+        let device_num = if pcm_dir.len() > 4 {
+            &pcm_dir[3..pcm_dir.len()-1]
+        } else {
+            "0"
+        };
+
+        let device_name = format!("hw:{},{}", card_num, device_num);
+        let mut device = AudioDeviceInfo::new(device_name, "ALSA".to_string());
+
+        // Try to get stream info from the USB streamN file (read-only, non-invasive)
+        let usb_stream = device_num.parse().ok()
+            .and_then(|num| usb_stream::read_usb_stream(card_path, num));
+        let usb_direction = usb_stream.as_ref().and_then(|s| match stream_type {
+            "PLAYBACK" => s.playback.as_ref(),
+            _ => s.capture.as_ref(),
+        });
+
+        let stream = match usb_direction {
+            Some(direction) => {
+                let min_channels = direction.altsets.iter().filter_map(|a| a.channels).min();
+                StreamCapabilities {
+                    // If we can't parse channels from stream, use conservative defaults
+                    min_channels: min_channels.unwrap_or(2),
+                    max_channels: direction.max_channels().unwrap_or(2),
+                    sample_rates: direction.sample_rates(),
+                    formats: direction.formats(),
+                    ..Default::default()
+                }
+            }
+            // Fallback: assume stereo if we can't read stream info
+            None => StreamCapabilities {
+                min_channels: 2,
+                max_channels: 2,
+                ..Default::default()
+            },
+        };
+        device.usb_stream = usb_stream;
+
+        match stream_type {
+            "PLAYBACK" => device.playback = Some(stream),
+            "CAPTURE" => device.capture = Some(stream),
+            _ => {}
+        }
+
+        device.update_from_streams();
+
+        // Check if device is in use from the per-subdevice state (read-only check)
+        let direction = if stream_type == "PLAYBACK" { "playback" } else { "capture" };
+        device.subdevices = pcm_status::read_direction_subdevices(&card_path.join(pcm_dir), direction);
+        device.in_use = if device.subdevices.is_empty() {
+            info_content.contains("subdevices_avail: 0")
+        } else {
+            device.subdevices.iter().any(|s| s.in_use)
+        };
+
+        return Some(device);
+    }
+
+    None
+}
+
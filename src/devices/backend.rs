@@ -0,0 +1,242 @@
+//! Pluggable device backends
+//!
+//! Each source of devices (CPAL, ALSA, the `/proc/asound` scanner) is a
+//! [`DeviceBackend`]. A [`BackendRegistry`] runs the selected backends in
+//! order, times them and records how each one fared, so one failing backend
+//! no longer hides the others. Crates outside this one can implement the
+//! trait and register their own backends next to the built-in ones.
+
+use std::time::Instant;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::sysroot::SysRoot;
+use super::AudioDeviceInfo;
+
+/// Settings every backend receives when it runs
+#[derive(Debug, Clone, Default)]
+pub struct ProbeOptions {
+    /// Do not read `/proc/asound`, so running streams cannot be disturbed
+    pub no_proc: bool,
+    /// Where `/proc` and `/sys` are read from
+    pub sysroot: SysRoot,
+}
+
+/// A source of audio devices
+pub trait DeviceBackend {
+    /// Short lowercase name used to select the backend, e.g. "alsa"
+    fn name(&self) -> &str;
+
+    /// Check whether the backend can run on this system, explaining why not
+    fn check_available(&self, _options: &ProbeOptions) -> Result<()> {
+        Ok(())
+    }
+
+    /// List every device the backend can see, with its capabilities
+    fn enumerate(&self, options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>>;
+
+    /// Read the capabilities of one device by name
+    ///
+    /// The default enumerates everything and picks the device out; backends
+    /// that can open a single device directly should override it.
+    fn probe(&self, name: &str, options: &ProbeOptions) -> Result<Option<AudioDeviceInfo>> {
+        Ok(self.enumerate(options)?.into_iter().find(|d| d.name == name))
+    }
+}
+
+/// How one backend fared during an interrogation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum BackendStatus {
+    Ok,
+    /// The availability check failed; the backend did not run
+    Unavailable { reason: String },
+    /// The backend ran and returned an error
+    Failed { error: String },
+}
+
+/// Outcome and timing of one backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendReport {
+    pub backend: String,
+    #[serde(flatten)]
+    pub status: BackendStatus,
+    /// Devices this backend added to the result
+    pub devices: usize,
+    /// Devices dropped because an earlier backend already reported them
+    pub duplicates: usize,
+    /// Wall-clock time spent in the availability check and enumeration
+    pub elapsed_ms: f64,
+}
+
+impl BackendReport {
+    /// "alsa: 14 devices in 132.5 ms" or "proc: unavailable (...)"
+    pub fn summary(&self) -> String {
+        match self.status {
+            BackendStatus::Ok => format!("{}: {} devices in {:.1} ms", self.backend, self.devices, self.elapsed_ms),
+            BackendStatus::Unavailable { ref reason } => format!("{}: unavailable ({})", self.backend, reason),
+            BackendStatus::Failed { ref error } => format!("{}: failed after {:.1} ms ({})", self.backend, self.elapsed_ms, error),
+        }
+    }
+}
+
+/// An ordered set of backends to interrogate
+///
+/// Earlier backends take precedence: a device a later backend reports under
+/// the same name and driver is dropped as a duplicate.
+#[derive(Default)]
+pub struct BackendRegistry {
+    backends: Vec<Box<dyn DeviceBackend>>,
+}
+
+impl BackendRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CPAL, ALSA and the `/proc/asound` scanner, in that order
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(super::cpal_devices::CpalBackend);
+        registry.register(super::alsa_devices::AlsaBackend);
+        registry.register(super::alsa_devices::ProcBackend);
+        registry
+    }
+
+    /// Add a backend after the ones already registered
+    pub fn register(&mut self, backend: impl DeviceBackend + 'static) {
+        self.backends.push(Box::new(backend));
+    }
+
+    /// Names of the registered backends, in order
+    pub fn names(&self) -> Vec<&str> {
+        self.backends.iter().map(|b| b.name()).collect()
+    }
+
+    /// Keep only the named backends, failing on names that are not registered
+    pub fn select(&mut self, names: &[&str]) -> Result<()> {
+        for name in names {
+            if !self.backends.iter().any(|b| b.name() == *name) {
+                bail!("Unknown backend: {} (available: {})", name, self.names().join(", "));
+            }
+        }
+        self.backends.retain(|b| names.contains(&b.name()));
+        Ok(())
+    }
+
+    /// Run every available backend and merge their devices
+    pub fn enumerate(&self, options: &ProbeOptions) -> (Vec<AudioDeviceInfo>, Vec<BackendReport>) {
+        let mut devices: Vec<AudioDeviceInfo> = Vec::new();
+        let mut reports = Vec::new();
+
+        for backend in &self.backends {
+            let start = Instant::now();
+            let result = backend.check_available(options)
+                .map_err(|e| BackendStatus::Unavailable { reason: format!("{:#}", e) })
+                .and_then(|()| backend.enumerate(options)
+                    .map_err(|e| BackendStatus::Failed { error: format!("{:#}", e) }));
+
+            let mut report = BackendReport {
+                backend: backend.name().to_string(),
+                status: BackendStatus::Ok,
+                devices: 0,
+                duplicates: 0,
+                elapsed_ms: 0.0,
+            };
+            match result {
+                Ok(found) => {
+                    let known = devices.len();
+                    for device in found {
+                        if devices[..known].iter().any(|d| d.name == device.name && d.driver == device.driver) {
+                            report.duplicates += 1;
+                        } else {
+                            devices.push(device);
+                        }
+                    }
+                    report.devices = devices.len() - known;
+                }
+                Err(status) => report.status = status,
+            }
+            report.elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+            reports.push(report);
+        }
+
+        (devices, reports)
+    }
+
+    /// Probe one device by name, asking each available backend in turn
+    ///
+    /// A backend error is only returned when no other backend knows the device.
+    pub fn probe(&self, name: &str, options: &ProbeOptions) -> Result<Option<AudioDeviceInfo>> {
+        let mut first_error = None;
+        for backend in &self.backends {
+            if backend.check_available(options).is_err() {
+                continue;
+            }
+            match backend.probe(name, options) {
+                Ok(Some(device)) => return Ok(Some(device)),
+                Ok(None) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e.context(format!("{} backend", backend.name())));
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, &'static [&'static str]);
+
+    impl DeviceBackend for Fixed {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn check_available(&self, options: &ProbeOptions) -> Result<()> {
+            if options.no_proc && self.0 == "proc" {
+                bail!("disabled");
+            }
+            Ok(())
+        }
+
+        fn enumerate(&self, _options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+            if self.1.is_empty() {
+                bail!("no devices");
+            }
+            Ok(self.1.iter().map(|name| AudioDeviceInfo::new(name.to_string(), "ALSA".to_string())).collect())
+        }
+    }
+
+    #[test]
+    fn merges_backends_and_reports_each() {
+        let mut registry = BackendRegistry::new();
+        registry.register(Fixed("alsa", &["default", "hw:0,0"]));
+        registry.register(Fixed("proc", &["hw:0,0", "hw:0,1"]));
+        registry.register(Fixed("broken", &[]));
+
+        let (devices, reports) = registry.enumerate(&ProbeOptions::default());
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["default", "hw:0,0", "hw:0,1"]);
+        assert_eq!((reports[1].devices, reports[1].duplicates), (1, 1));
+        assert_eq!(reports[2].status, BackendStatus::Failed { error: "no devices".to_string() });
+
+        let options = ProbeOptions { no_proc: true, ..Default::default() };
+        let (devices, reports) = registry.enumerate(&options);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(reports[1].status, BackendStatus::Unavailable { reason: "disabled".to_string() });
+        assert!(registry.probe("hw:0,1", &options).is_err());
+        assert_eq!(registry.probe("hw:0,1", &ProbeOptions::default()).unwrap().map(|d| d.name), Some("hw:0,1".to_string()));
+
+        assert!(registry.select(&["alsa", "pulse"]).is_err());
+        registry.select(&["proc"]).unwrap();
+        assert_eq!(registry.names(), ["proc"]);
+    }
+}
@@ -0,0 +1,143 @@
+//! Sound card lookup
+//!
+//! Card numbers, ids and descriptions from `/proc/asound`, and the
+//! conversions between ALSA device names and the cards they belong to.
+
+use std::collections::HashMap;
+use anyhow::Result;
+
+use crate::sysroot::SysRoot;
+use crate::{eld, hda_codec, sysfs};
+use super::CardInfo;
+
+/// Card number to card id ("0" -> "PCH"), from `/proc/asound/cards`
+pub fn get_card_mapping(root: &SysRoot) -> Result<HashMap<String, String>> {
+    use std::fs;
+    let mut mapping = HashMap::new();
+
+    if let Ok(contents) = fs::read_to_string(root.asound().join("cards")) {
+        for line in contents.lines() {
+            // Parse lines like " 0 [HDMI           ]: HDA-Intel - HDA ATI HDMI"
+            if let Some(stripped) = line.strip_prefix(' ') {
+                if let Some(bracket_start) = stripped.find('[') {
+                    if let Some(bracket_end) = stripped.find(']') {
+                        if let Some(card_num) = stripped.split_whitespace().next() {
+                            let card_name = stripped[bracket_start+1..bracket_end].trim().to_string();
+                            mapping.insert(card_num.to_string(), card_name);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(mapping)
+}
+
+/// Resolve an optional `--card` value, failing on cards that do not exist
+pub fn card_filter_index(root: &SysRoot, card_filter: Option<&str>) -> Result<Option<u32>> {
+    match card_filter {
+        Some(card_id) => match resolve_card_number(root, card_id) {
+            Some(card) => Ok(Some(card)),
+            None => anyhow::bail!("Unknown card: {}", card_id),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Card index for "0", "card0" or a card id such as "PCH"
+pub fn resolve_card_number(root: &SysRoot, card_id: &str) -> Option<u32> {
+    let card_num = card_id.strip_prefix("card").unwrap_or(card_id);
+    if let Ok(card) = card_num.parse() {
+        return Some(card);
+    }
+
+    let card_mapping = get_card_mapping(root).unwrap_or_default();
+    card_mapping.iter()
+        .find(|(_, name)| name.eq_ignore_ascii_case(card_id))
+        .and_then(|(num, _)| num.parse().ok())
+        .or_else(|| {
+            // Stable card IDs such as "usb-1235_8210-Y8XXXXX0123456"
+            card_mapping.keys()
+                .filter_map(|num| num.parse().ok())
+                .find(|&card| sysfs::read_card_identity(&root.sys(), card)
+                    .is_some_and(|identity| identity.stable_id.as_deref() == Some(card_id)))
+        })
+}
+
+/// Card id to its long description, from `/proc/asound/cards`
+pub fn get_card_descriptions(root: &SysRoot) -> Result<HashMap<String, String>> {
+    use std::fs;
+    let mut descriptions = HashMap::new();
+
+    if let Ok(contents) = fs::read_to_string(root.asound().join("cards")) {
+        for line in contents.lines() {
+            // Parse lines like " 0 [HDMI           ]: HDA-Intel - HDA ATI HDMI"
+            if let Some(stripped) = line.strip_prefix(' ') {
+                if let Some(bracket_start) = stripped.find('[') {
+                    if let Some(bracket_end) = stripped.find(']') {
+                        if let Some(dash_pos) = stripped.find(" - ") {
+                            let card_name = stripped[bracket_start+1..bracket_end].trim().to_string();
+                            let description = stripped[dash_pos+3..].trim().to_string();
+                            descriptions.insert(card_name, description);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(descriptions)
+}
+
+/// PCM device number from "hw:1,0" or "...:CARD=USB,DEV=0" style names
+pub fn pcm_index_from_name(name: &str) -> Option<u32> {
+    if let Some(pos) = name.find("DEV=") {
+        return name[pos + 4..].split(',').next()?.parse().ok();
+    }
+    if name.contains("CARD=") {
+        return None;
+    }
+
+    let (_, params) = name.split_once(':')?;
+    params.split(',').nth(1)?.parse().ok()
+}
+
+/// Card index from "hw:1,0", "plughw:1,0" or "...:CARD=USB,DEV=0" style names
+pub fn card_index_from_name(name: &str, card_mapping: &HashMap<String, String>) -> Option<u32> {
+    if let Some(pos) = name.find("CARD=") {
+        let card_name = name[pos + 5..].split(',').next()?;
+        return card_mapping.iter()
+            .find(|(_, id)| *id == card_name)
+            .and_then(|(num, _)| num.parse().ok())
+            .or_else(|| card_name.parse().ok());
+    }
+
+    let (_, params) = name.split_once(':')?;
+    params.split(',').next()?.parse().ok()
+}
+
+/// Read the per-card files of every card in /proc/asound (read-only)
+pub fn get_card_infos(root: &SysRoot) -> Vec<CardInfo> {
+    use std::fs;
+
+    let Ok(entries) = fs::read_dir(root.asound()) else { return Vec::new() };
+
+    let mut cards: Vec<CardInfo> = entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let index = name.to_str()?.strip_prefix("card")?.parse().ok()?;
+            let card_path = entry.path();
+            let hda_codecs = hda_codec::read_hda_codecs(&card_path);
+            Some(CardInfo {
+                index,
+                id: fs::read_to_string(card_path.join("id")).map(|id| id.trim().to_string()).unwrap_or_default(),
+                elds: eld::read_elds(&card_path, root.is_host().then_some(index), &hda_codecs),
+                hda_codecs,
+            })
+        })
+        .collect();
+
+    cards.sort_by_key(|c| c.index);
+    cards
+}
@@ -0,0 +1,131 @@
+//! CPAL backend
+//!
+//! Cross-platform device list from CPAL's default host. On Linux this sees
+//! what the ALSA configuration exposes to ordinary applications, with the
+//! stream configurations CPAL is willing to open.
+
+use anyhow::{bail, Result};
+
+use super::backend::{DeviceBackend, ProbeOptions};
+use super::{buffer_sizes_for_range, default_size_in_range, AudioDeviceInfo, StreamCapabilities};
+
+/// Devices of CPAL's default host
+pub struct CpalBackend;
+
+impl DeviceBackend for CpalBackend {
+    fn name(&self) -> &str {
+        "cpal"
+    }
+
+    fn check_available(&self, options: &ProbeOptions) -> Result<()> {
+        if !options.sysroot.is_host() {
+            bail!("probes the running system, not a sysroot");
+        }
+        if cpal::available_hosts().is_empty() {
+            bail!("no audio host compiled in");
+        }
+        Ok(())
+    }
+
+    fn enumerate(&self, _options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+        get_cpal_devices()
+    }
+
+    fn probe(&self, name: &str, _options: &ProbeOptions) -> Result<Option<AudioDeviceInfo>> {
+        use cpal::traits::{DeviceTrait, HostTrait};
+
+        let device = cpal::default_host().devices()?
+            .find(|device| device.name().is_ok_and(|n| n == name));
+        Ok(device.map(|device| cpal_device_info(&device)))
+    }
+}
+
+/// Enumerate every device of the default CPAL host
+pub fn get_cpal_devices() -> Result<Vec<AudioDeviceInfo>> {
+    use cpal::traits::HostTrait;
+
+    // Get the default host
+    let host = cpal::default_host();
+
+    // Iterate through all available devices
+    Ok(host.devices()?.map(|device| cpal_device_info(&device)).collect())
+}
+
+/// Read both directions of one CPAL device
+fn cpal_device_info(device: &cpal::Device) -> AudioDeviceInfo {
+    use cpal::traits::DeviceTrait;
+
+    let device_name = device.name().unwrap_or_else(|_| "Unknown Device".to_string());
+
+    // Buffer size constraints are shared by both directions in the flat fields
+    let mut buffer_range: Option<(u32, u32)> = None;
+
+    // Capture and playback are read independently so duplex devices keep both
+    let capture = cpal_stream_capabilities(
+        device.supported_input_configs().ok().into_iter().flatten(),
+        device.default_input_config().ok(),
+        &mut buffer_range,
+    );
+    let playback = cpal_stream_capabilities(
+        device.supported_output_configs().ok().into_iter().flatten(),
+        device.default_output_config().ok(),
+        &mut buffer_range,
+    );
+
+    let (supported_buffer_sizes, buffer_size_source) = buffer_sizes_for_range(buffer_range);
+
+    let mut info = AudioDeviceInfo::new(device_name, "CPAL".to_string());
+    info.supported_buffer_sizes = supported_buffer_sizes;
+    info.buffer_size_source = buffer_size_source;
+    // CPAL only exposes a single buffer size range
+    info.default_buffer_size = default_size_in_range(buffer_range);
+    info.playback = playback;
+    info.capture = capture;
+    info.update_from_streams();
+    info
+}
+
+/// Collect one direction's capabilities from CPAL's supported config ranges
+fn cpal_stream_capabilities(
+    configs: impl Iterator<Item = cpal::SupportedStreamConfigRange>,
+    default_config: Option<cpal::SupportedStreamConfig>,
+    buffer_range: &mut Option<(u32, u32)>,
+) -> Option<StreamCapabilities> {
+    let mut stream = StreamCapabilities::default();
+    let mut found = false;
+
+    for config in configs {
+        let channels = config.channels() as u32;
+        stream.min_channels = if found { stream.min_channels.min(channels) } else { channels };
+        stream.max_channels = stream.max_channels.max(channels);
+        stream.sample_rates.push(config.min_sample_rate().0);
+        stream.sample_rates.push(config.max_sample_rate().0);
+
+        let format = config.sample_format().to_string();
+        if !stream.formats.contains(&format) {
+            stream.formats.push(format);
+        }
+
+        if let cpal::SupportedBufferSize::Range { min, max } = *config.buffer_size() {
+            *buffer_range = Some(match *buffer_range {
+                Some((lo, hi)) => (lo.min(min), hi.max(max)),
+                None => (min, max),
+            });
+        }
+
+        found = true;
+    }
+
+    if let Some(default_config) = default_config {
+        stream.default_sample_rate = Some(default_config.sample_rate().0);
+        stream.default_channels = Some(default_config.channels() as u32);
+        stream.default_format = Some(default_config.sample_format().to_string());
+        found = true;
+    }
+
+    // Remove duplicates and sort
+    stream.sample_rates.sort_unstable();
+    stream.sample_rates.dedup();
+
+    found.then_some(stream)
+}
@@ -3,17 +3,241 @@
 //! This module contains the core functionality for interrogating audio devices
 //! across different platforms and audio systems.
 
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+
+use crate::sysroot::SysRoot;
+use crate::{defaults, sysfs};
+
 pub mod types;
+pub mod backend;
+pub mod cards;
 pub mod cpal_devices;
 pub mod alsa_devices;
 
 pub use types::*;
+pub use backend::{BackendRegistry, BackendReport, BackendStatus, DeviceBackend, ProbeOptions};
+pub use cards::{card_filter_index, get_card_infos, resolve_card_number};
+use cards::{card_index_from_name, get_card_descriptions, get_card_mapping, pcm_index_from_name};
 pub use cpal_devices::get_cpal_devices;
 
 #[cfg(target_os = "linux")]
 pub use alsa_devices::get_alsa_devices;
 
 #[cfg(not(target_os = "linux"))]
-pub fn get_alsa_devices() -> anyhow::Result<Vec<AudioDeviceInfo>> {
-    Ok(Vec::new())
+pub fn get_alsa_devices(_options: &ProbeOptions) -> Result<Vec<AudioDeviceInfo>> {
+    Ok(Vec::new()) // ALSA is Linux-specific
+}
+
+/// Interrogate every built-in backend and assemble the full system report
+///
+/// With `no_proc` set nothing under `/proc/asound` is read, so streams that
+/// are running cannot be disturbed; busy PCMs are then simply missing.
+pub fn get_system_audio_info(no_proc: bool) -> Result<SystemAudioInfo> {
+    let options = ProbeOptions { no_proc, ..Default::default() };
+    Ok(interrogate(&BackendRegistry::with_builtin(), &options))
+}
+
+/// Run the backends of a registry and assemble the system report
+///
+/// Backend failures do not abort the interrogation; they are recorded in
+/// [`SystemAudioInfo::backends`] next to each backend's timing.
+pub fn interrogate(registry: &BackendRegistry, options: &ProbeOptions) -> SystemAudioInfo {
+    let (mut all_devices, backends) = registry.enumerate(options);
+
+    let root = &options.sysroot;
+    attach_card_identities(&mut all_devices, root);
+    let cards = if options.no_proc { Vec::new() } else { get_card_infos(root) };
+    attach_hdmi_sinks(&mut all_devices, &cards, root);
+
+    // Resolve default devices from the sound server, ALSA configuration or CPAL;
+    // none of them describe a recorded tree
+    let (default_input, default_output) = if root.is_host() {
+        defaults::resolve_default_devices()
+    } else {
+        (None, None)
+    };
+
+    let mut info = SystemAudioInfo {
+        devices: all_devices,
+        default_input: default_input.as_ref().map(|d| d.name.clone()),
+        default_input_source: default_input.map(|d| d.source),
+        default_output: default_output.as_ref().map(|d| d.name.clone()),
+        default_output_source: default_output.map(|d| d.source),
+        total_input_devices: 0,
+        total_output_devices: 0,
+        cards,
+        backends,
+    };
+    info.update_totals();
+    info
+}
+
+/// Attach the ELD of each HDMI/DisplayPort PCM to the hardware devices using it
+///
+/// Only direct hardware names are matched: the DEV= of plugins such as
+/// `hdmi:CARD=PCH,DEV=1` counts HDMI outputs, not PCM devices.
+fn attach_hdmi_sinks(devices: &mut [AudioDeviceInfo], cards: &[CardInfo], root: &SysRoot) {
+    let card_mapping = get_card_mapping(root).unwrap_or_default();
+
+    for device in devices.iter_mut() {
+        if !(device.name.starts_with("hw:") || device.name.starts_with("plughw:")) {
+            continue;
+        }
+        let (Some(card), Some(pcm)) = (card_index_from_name(&device.name, &card_mapping), pcm_index_from_name(&device.name)) else { continue };
+        device.hdmi_sink = cards.iter()
+            .find(|c| c.index == card)
+            .and_then(|c| c.elds.iter().find(|e| e.pcm_device == Some(pcm)))
+            .cloned();
+    }
+}
+
+/// Link every card-backed device to its sysfs hardware identity and stable ID
+///
+/// Devices not tied to a card (`default`, `pulse`, `null`, ...) are defined by
+/// name in the ALSA configuration, so the name itself is their stable ID.
+pub fn attach_card_identities(devices: &mut [AudioDeviceInfo], root: &SysRoot) {
+    let card_mapping = get_card_mapping(root).unwrap_or_default();
+    let mut identities: HashMap<u32, Option<sysfs::CardIdentity>> = HashMap::new();
+
+    for device in devices.iter_mut() {
+        let Some(card) = card_index_from_name(&device.name, &card_mapping) else {
+            device.stable_id = Some(device.name.clone());
+            continue;
+        };
+        device.hardware = identities.entry(card)
+            .or_insert_with(|| sysfs::read_card_identity(&root.sys(), card))
+            .clone();
+
+        // Plugins on top of the hardware ("plughw", "front", ...) keep their prefix
+        let pcm_id = device.hardware.as_ref().and_then(|h| h.pcm_stable_id(pcm_index_from_name(&device.name)));
+        device.stable_id = pcm_id.map(|id| match device.name.split_once(':') {
+            Some((plugin, _)) if plugin != "hw" => format!("{}@{}", plugin, id),
+            _ => id,
+        });
+    }
+}
+
+/// Keep the devices of one card and/or matching a name, like `--card`/`--device`
+///
+/// Unless `show_all` is set, virtual duplicates (`dmix`, `dsnoop`, `surround*`,
+/// `iec958`, and `plughw` twins of `hw` devices) are dropped as well.
+pub fn filter_devices(
+    devices: Vec<AudioDeviceInfo>,
+    root: &SysRoot,
+    card_filter: Option<&str>,
+    device_filter: Option<&str>,
+    show_all: bool
+) -> Vec<AudioDeviceInfo> {
+    let mut filtered = devices;
+
+    // Apply card filter
+    if let Some(card_id) = card_filter {
+        let card_num = if card_id.starts_with("card") {
+            card_id.strip_prefix("card").unwrap_or(card_id)
+        } else {
+            card_id
+        };
+
+        // Get card mapping from system
+        let card_mapping = get_card_mapping(root).unwrap_or_default();
+        let target_card_name = card_mapping.get(card_num).cloned();
+
+        filtered.retain(|device| {
+            // Match by card number in various formats
+            device.name.contains(&format!("hw:{}", card_num)) ||
+            device.name.contains(&format!("card{}", card_num)) ||
+            // Match by card name if we found it
+            (target_card_name.as_ref().is_some_and(|name| device.name.contains(&format!("CARD={}", name)))) ||
+            // Direct match for card name
+            device.name.contains(&format!("CARD={}", card_id)) ||
+            // Stable card ID, which survives renumbering
+            device.hardware.as_ref().is_some_and(|h| h.stable_id.as_deref() == Some(card_id))
+        });
+    }
+
+    // Apply device name filter
+    if let Some(name_filter) = device_filter {
+        let name_lower = name_filter.to_lowercase();
+        let card_descriptions = get_card_descriptions(root).unwrap_or_default();
+
+        filtered.retain(|device| {
+            // First check device name and description
+            if device.name.to_lowercase().contains(&name_lower) ||
+               device.stable_id.as_ref().is_some_and(|id| id.to_lowercase().contains(&name_lower)) ||
+               device.description.as_ref().is_some_and(|d| d.to_lowercase().contains(&name_lower)) {
+                return true;
+            }
+
+            // Then check if any card description matches and this device belongs to that card
+            for (card_name, description) in &card_descriptions {
+                if description.to_lowercase().contains(&name_lower) &&
+                   device.name.contains(&format!("CARD={}", card_name)) {
+                    return true;
+                }
+            }
+
+            false
+        });
+    }
+
+    // If not showing all, remove common duplicates
+    if !show_all {
+        let mut seen_names = HashSet::new();
+        filtered.retain(|device| {
+            // Skip obvious virtual/duplicate devices unless specifically requested
+            if device.name.starts_with("dmix:") ||
+               device.name.starts_with("dsnoop:") ||
+               device.name.starts_with("surround") ||
+               device.name.starts_with("iec958:") {
+                return false;
+            }
+
+            // For similar devices, prefer the simpler name
+            let simplified_name = if device.name.starts_with("plughw:") {
+                device.name.replace("plughw:", "hw:")
+            } else {
+                device.name.clone()
+            };
+
+            if seen_names.contains(&simplified_name) {
+                false
+            } else {
+                seen_names.insert(simplified_name);
+                true
+            }
+        });
+    }
+
+    filtered
+}
+
+/// Power-of-two sizes inside a measured frame range, or the assumed list without one
+pub(crate) fn buffer_sizes_for_range(range: Option<(u32, u32)>) -> (Vec<u32>, ValueSource) {
+    let Some((min, max)) = range else {
+        return (ASSUMED_BUFFER_SIZES.to_vec(), ValueSource::Assumed);
+    };
+
+    let mut sizes: Vec<u32> = (4..=20)
+        .map(|shift| 1u32 << shift)
+        .filter(|&size| size >= min && size <= max)
+        .collect();
+
+    // Odd ranges (e.g. USB devices with 44 or 88 frame periods) may hold no power of two
+    if sizes.is_empty() {
+        sizes.push(min);
+        if max != min {
+            sizes.push(max);
+        }
+    }
+
+    (sizes, ValueSource::Probed)
+}
+
+/// 1024 frames clamped into the measured range, as a conventional starting point
+pub(crate) fn default_size_in_range(range: Option<(u32, u32)>) -> u32 {
+    match range {
+        Some((min, max)) if min <= max => 1024u32.clamp(min, max),
+        _ => 1024,
+    }
 }
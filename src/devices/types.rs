@@ -5,11 +5,24 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::{chmap, defaults, eld, hda_codec, jacks, pcm_status, sysfs, usb_stream};
+use super::backend::BackendReport;
+
+/// Buffer sizes assumed when a backend gives no constraints
+pub const ASSUMED_BUFFER_SIZES: [u32; 7] = [64, 128, 256, 512, 1024, 2048, 4096];
+
 /// Information about a single audio device
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioDeviceInfo {
     /// Human-readable device name
     pub name: String,
+    /// Identifier that survives reboots and card renumbering, derived from the
+    /// card's USB serial, port path or PCI address plus the PCM number
+    #[serde(default)]
+    pub stable_id: Option<String>,
+    /// Description from the ALSA name hints
+    #[serde(default)]
+    pub description: Option<String>,
     /// Type of device (Input, Output, or Input/Output)
     pub device_type: String,
     /// Number of input channels available
@@ -20,12 +33,92 @@ pub struct AudioDeviceInfo {
     pub supported_sample_rates: Vec<u32>,
     /// List of supported buffer sizes in samples
     pub supported_buffer_sizes: Vec<u32>,
+    /// Probed when the buffer sizes come from the device's limits, assumed when they are a fixed guess
+    #[serde(default)]
+    pub buffer_size_source: ValueSource,
+    /// List of supported period sizes in samples; only the ALSA backend reports them
+    #[serde(default)]
+    pub supported_period_sizes: Vec<u32>,
+    /// Probed when the period sizes come from the hw_params limits, assumed when they are the fallback list
+    #[serde(default)]
+    pub period_size_source: ValueSource,
     /// Default sample rate in Hz
     pub default_sample_rate: u32,
     /// Default buffer size in samples
     pub default_buffer_size: u32,
     /// Audio driver/system name (CPAL, ALSA, etc.)
     pub driver: String,
+    /// Playback capabilities; `None` when the PCM cannot be opened for playback
+    #[serde(default)]
+    pub playback: Option<StreamCapabilities>,
+    /// Capture capabilities; `None` when the PCM cannot be opened for capture
+    #[serde(default)]
+    pub capture: Option<StreamCapabilities>,
+    /// Parsed `/proc/asound/cardN/streamM` for USB audio devices
+    #[serde(default)]
+    pub usb_stream: Option<usb_stream::UsbStream>,
+    /// True when an application has any subdevice of this PCM open
+    #[serde(default)]
+    pub in_use: bool,
+    /// Live parameters and state of every subdevice, from `/proc/asound`
+    #[serde(default)]
+    pub subdevices: Vec<pcm_status::PcmSubdevice>,
+    /// Hardware identity of the card from sysfs (IDs, serial, bus path, kernel driver)
+    #[serde(default)]
+    pub hardware: Option<sysfs::CardIdentity>,
+    /// Monitor connected to this HDMI/DisplayPort PCM, from its ELD
+    #[serde(default)]
+    pub hdmi_sink: Option<eld::Eld>,
+    /// Plug state of the connectors belonging to this PCM
+    #[serde(default)]
+    pub jacks: Vec<jacks::JackState>,
+}
+
+/// Whether a reported value was measured on the device or filled in as a guess
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueSource {
+    Probed,
+    #[default]
+    Assumed,
+}
+
+/// Capabilities of one stream direction (playback or capture) of a device
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamCapabilities {
+    pub min_channels: u32,
+    pub max_channels: u32,
+    pub sample_rates: Vec<u32>,
+    pub formats: Vec<String>,
+    pub default_sample_rate: Option<u32>,
+    pub default_channels: Option<u32>,
+    pub default_format: Option<String>,
+    /// Full ALSA hw_params space, when the stream was probed through ALSA
+    #[serde(default)]
+    pub hw: Option<HwCapabilities>,
+}
+
+/// Hardware parameter space of one PCM stream direction, read without restrictions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HwCapabilities {
+    pub formats: Vec<String>,
+    pub access_types: Vec<String>,
+    pub min_channels: u32,
+    pub max_channels: u32,
+    pub min_rate: u32,
+    pub max_rate: u32,
+    pub sample_rates: Vec<u32>,
+    pub min_period_size: Option<u64>,
+    pub max_period_size: Option<u64>,
+    pub min_buffer_size: Option<u64>,
+    pub max_buffer_size: Option<u64>,
+    pub min_periods: Option<u32>,
+    pub max_periods: Option<u32>,
+    pub min_buffer_bytes: Option<u64>,
+    pub max_buffer_bytes: Option<u64>,
+    /// Speaker positions the channels can carry
+    #[serde(default)]
+    pub channel_maps: chmap::ChannelMaps,
 }
 
 /// System-wide audio information
@@ -35,12 +128,63 @@ pub struct SystemAudioInfo {
     pub devices: Vec<AudioDeviceInfo>,
     /// Name of the default input device, if any
     pub default_input: Option<String>,
+    /// Where the default input was found (sound server, ALSA config, CPAL)
+    #[serde(default)]
+    pub default_input_source: Option<defaults::DefaultSource>,
     /// Name of the default output device, if any
     pub default_output: Option<String>,
+    /// Where the default output was found (sound server, ALSA config, CPAL)
+    #[serde(default)]
+    pub default_output_source: Option<defaults::DefaultSource>,
     /// Total number of devices with input capabilities
     pub total_input_devices: usize,
     /// Total number of devices with output capabilities
     pub total_output_devices: usize,
+    /// Per-card details that do not belong to a single PCM device
+    #[serde(default)]
+    pub cards: Vec<CardInfo>,
+    /// Which backends ran, how long they took and how they failed
+    #[serde(default)]
+    pub backends: Vec<BackendReport>,
+}
+
+/// A sound card and what `/proc/asound/cardN` says about it beyond its PCMs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardInfo {
+    pub index: u32,
+    /// Short card id, e.g. "PCH" or "USB"
+    pub id: String,
+    /// Parsed `codec#M` files of HD Audio cards
+    #[serde(default)]
+    pub hda_codecs: Vec<hda_codec::HdaCodec>,
+    /// HDMI/DisplayPort sinks from the `eld#X.Y` files
+    #[serde(default)]
+    pub elds: Vec<eld::Eld>,
+}
+
+impl ValueSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ValueSource::Probed => "probed",
+            ValueSource::Assumed => "assumed",
+        }
+    }
+}
+
+impl StreamCapabilities {
+    /// Summarise an ALSA hw_params space; raw hardware has no defaults
+    pub fn from_hw(hw: HwCapabilities) -> Self {
+        Self {
+            min_channels: hw.min_channels,
+            max_channels: hw.max_channels,
+            sample_rates: hw.sample_rates.clone(),
+            formats: hw.formats.clone(),
+            default_sample_rate: None,
+            default_channels: None,
+            default_format: None,
+            hw: Some(hw),
+        }
+    }
 }
 
 impl AudioDeviceInfo {
@@ -48,17 +192,62 @@ impl AudioDeviceInfo {
     pub fn new(name: String, driver: String) -> Self {
         Self {
             name,
+            stable_id: None,
+            description: None,
             device_type: "Unknown".to_string(),
             input_channels: 0,
             output_channels: 0,
             supported_sample_rates: Vec::new(),
-            supported_buffer_sizes: vec![64, 128, 256, 512, 1024, 2048, 4096],
+            supported_buffer_sizes: ASSUMED_BUFFER_SIZES.to_vec(),
+            buffer_size_source: ValueSource::Assumed,
+            supported_period_sizes: Vec::new(),
+            period_size_source: ValueSource::Assumed,
             default_sample_rate: 44100,
             default_buffer_size: 1024,
             driver,
+            playback: None,
+            capture: None,
+            usb_stream: None,
+            in_use: false,
+            subdevices: Vec::new(),
+            hardware: None,
+            hdmi_sink: None,
+            jacks: Vec::new(),
         }
     }
 
+    /// Recompute the flat summary fields from the per-direction blocks
+    ///
+    /// Channel counts prefer the stream's default over its maximum, and the
+    /// default sample rate prefers playback over capture.
+    pub fn update_from_streams(&mut self) {
+        let channels = |stream: &Option<StreamCapabilities>| {
+            stream.as_ref().map_or(0, |s| s.default_channels.unwrap_or(s.max_channels))
+        };
+        self.output_channels = channels(&self.playback);
+        self.input_channels = channels(&self.capture);
+
+        let mut rates: Vec<u32> = self.playback.iter()
+            .chain(self.capture.iter())
+            .flat_map(|s| s.sample_rates.iter().copied())
+            .collect();
+        rates.sort_unstable();
+        rates.dedup();
+        self.supported_sample_rates = rates;
+
+        let default_rate = |stream: &Option<StreamCapabilities>| stream.as_ref().and_then(|s| s.default_sample_rate);
+        if let Some(rate) = default_rate(&self.playback).or(default_rate(&self.capture)) {
+            self.default_sample_rate = rate;
+        }
+
+        self.update_device_type();
+    }
+
+    /// Whether `id` names this device, either by name or stable ID
+    pub fn matches_id(&self, id: &str) -> bool {
+        self.name == id || self.stable_id.as_deref() == Some(id)
+    }
+
     /// Check if this device has input capabilities
     pub fn has_input(&self) -> bool {
         self.input_channels > 0
@@ -96,27 +285,10 @@ impl AudioDeviceInfo {
 }
 
 impl SystemAudioInfo {
-    /// Create a new SystemAudioInfo from a list of devices
-    pub fn from_devices(devices: Vec<AudioDeviceInfo>) -> Self {
-        let input_count = devices.iter().filter(|d| d.has_input()).count();
-        let output_count = devices.iter().filter(|d| d.has_output()).count();
-
-        // Try to determine default devices
-        let default_input = devices.iter()
-            .find(|d| d.has_input() && (d.name.contains("default") || d.name.contains("hw:0")))
-            .map(|d| d.name.clone());
-
-        let default_output = devices.iter()
-            .find(|d| d.has_output() && (d.name.contains("default") || d.name.contains("hw:0")))
-            .map(|d| d.name.clone());
-
-        Self {
-            devices,
-            default_input,
-            default_output,
-            total_input_devices: input_count,
-            total_output_devices: output_count,
-        }
+    /// Recompute the input and output totals, e.g. after filtering `devices`
+    pub fn update_totals(&mut self) {
+        self.total_input_devices = self.input_devices().count();
+        self.total_output_devices = self.output_devices().count();
     }
 
     /// Get all input devices
@@ -129,13 +301,13 @@ impl SystemAudioInfo {
         self.devices.iter().filter(|d| d.has_output())
     }
 
-    /// Find a device by name
-    pub fn find_device(&self, name: &str) -> Option<&AudioDeviceInfo> {
-        self.devices.iter().find(|d| d.name == name)
+    /// Find a device by name or stable ID
+    pub fn find_device(&self, id: &str) -> Option<&AudioDeviceInfo> {
+        self.devices.iter().find(|d| d.matches_id(id))
     }
 
     /// Get devices by driver type
-    pub fn devices_by_driver(&self, driver: &str) -> impl Iterator<Item = &AudioDeviceInfo> {
+    pub fn devices_by_driver<'a>(&'a self, driver: &'a str) -> impl Iterator<Item = &'a AudioDeviceInfo> {
         self.devices.iter().filter(move |d| d.driver == driver)
     }
 }
@@ -0,0 +1,333 @@
+//! Comparing two `--json` snapshots
+//!
+//! Card numbers change with plug order, so devices are not matched by name.
+//! A device's key is its driver, plugin prefix, card and PCM number, where
+//! the card is the USB vendor:product (and serial) for USB hardware and the
+//! card id string ("PCH", "HDMI") otherwise. Matched devices are compared
+//! field by field; the defaults of the whole system are compared by the key of
+//! the device they name. A device that only changed its name (the card number
+//! in "hw:1,0") is shown, but is not a difference.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::devices::cards::{card_index_from_name, pcm_index_from_name};
+use crate::{AudioDeviceInfo, SystemAudioInfo};
+
+/// Device fields compared between snapshots, as paths into the device's JSON
+const COMPARED_FIELDS: [&str; 13] = [
+    "device_type",
+    "input_channels",
+    "output_channels",
+    "supported_sample_rates",
+    "default_sample_rate",
+    "default_buffer_size",
+    "playback.max_channels",
+    "playback.default_channels",
+    "playback.default_format",
+    "capture.max_channels",
+    "capture.default_channels",
+    "capture.default_format",
+    "in_use",
+];
+
+/// A device present in only one snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceEntry {
+    pub key: String,
+    pub name: String,
+    pub driver: String,
+}
+
+/// A device present in both snapshots whose fields or name differ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceChanges {
+    pub key: String,
+    pub old_name: String,
+    pub new_name: String,
+    pub driver: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// One field's value in the old and the new snapshot; `null` when absent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Everything that differs between two snapshots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub added: Vec<DeviceEntry>,
+    pub removed: Vec<DeviceEntry>,
+    /// Matched devices that differ; renamed ones alone have no `changes`
+    pub changed: Vec<DeviceChanges>,
+    /// Changes to the system's default input and output
+    pub defaults: Vec<FieldChange>,
+    /// Devices found in both snapshots under the same name with no differences
+    pub unchanged: usize,
+    /// Added and removed devices plus every changed field, defaults included
+    pub differences: usize,
+}
+
+/// Read a snapshot written by `--json`
+pub fn load_snapshot(path: &Path) -> Result<SystemAudioInfo> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("{} is not an audio-interrogator JSON report", path.display()))
+}
+
+/// What identifies `device` in any snapshot, e.g. "ALSA/hw:PCH,3" or
+/// "ALSA/plughw:usb-1235:8210-Y8XXXXX0123456,0"
+pub fn device_key(device: &AudioDeviceInfo, card_ids: &HashMap<String, String>) -> String {
+    let fallback = || format!("{}/{}", device.driver, device.name);
+    let Some((plugin, _)) = device.name.split_once(':') else { return fallback() };
+    let Some(card) = card_index_from_name(&device.name, card_ids) else { return fallback() };
+
+    let usb = device.hardware.as_ref()
+        .filter(|h| h.bus.as_deref() == Some("usb"))
+        .and_then(|h| Some(match h.serial {
+            Some(ref serial) => format!("usb-{}-{}", h.id_pair()?, serial),
+            None => format!("usb-{}", h.id_pair()?),
+        }));
+    let card = usb
+        .or_else(|| card_ids.get(&card.to_string()).cloned())
+        .unwrap_or_else(|| format!("card{}", card));
+
+    match pcm_index_from_name(&device.name) {
+        Some(pcm) => format!("{}/{}:{},{}", device.driver, plugin, card, pcm),
+        None => format!("{}/{}:{}", device.driver, plugin, card),
+    }
+}
+
+fn card_ids(info: &SystemAudioInfo) -> HashMap<String, String> {
+    info.cards.iter().map(|c| (c.index.to_string(), c.id.clone())).collect()
+}
+
+/// Key of the device a default names, or the name itself when it is not in
+/// the device list (sound server sinks, plugin PCMs)
+fn default_key(info: &SystemAudioInfo, name: &str) -> String {
+    info.find_device(name)
+        .map(|device| device_key(device, &card_ids(info)))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Compare two snapshots
+pub fn diff_snapshots(old: &SystemAudioInfo, new: &SystemAudioInfo) -> SnapshotDiff {
+    let keyed = |info: &SystemAudioInfo| -> Vec<(String, Value, AudioDeviceInfo)> {
+        let card_ids = card_ids(info);
+        info.devices.iter()
+            .map(|d| (device_key(d, &card_ids), serde_json::to_value(d).unwrap_or(Value::Null), d.clone()))
+            .collect()
+    };
+    let mut old_devices = keyed(old);
+    let new_devices = keyed(new);
+
+    let entry = |key: String, device: &AudioDeviceInfo| DeviceEntry {
+        key,
+        name: device.name.clone(),
+        driver: device.driver.clone(),
+    };
+
+    let mut diff = SnapshotDiff {
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+        defaults: Vec::new(),
+        unchanged: 0,
+        differences: 0,
+    };
+
+    for (key, new_value, new_device) in new_devices {
+        // Identical keys (two interfaces without serials) pair up in order
+        let Some(position) = old_devices.iter().position(|(k, _, _)| *k == key) else {
+            diff.added.push(entry(key, &new_device));
+            continue;
+        };
+        let (_, old_value, old_device) = old_devices.remove(position);
+
+        let changes: Vec<FieldChange> = COMPARED_FIELDS.iter()
+            .filter_map(|field| {
+                let pointer = format!("/{}", field.replace('.', "/"));
+                let old = old_value.pointer(&pointer).cloned().unwrap_or(Value::Null);
+                let new = new_value.pointer(&pointer).cloned().unwrap_or(Value::Null);
+                (old != new).then(|| FieldChange { field: field.to_string(), old, new })
+            })
+            .collect();
+
+        if changes.is_empty() && old_device.name == new_device.name {
+            diff.unchanged += 1;
+        } else {
+            diff.changed.push(DeviceChanges {
+                key,
+                old_name: old_device.name,
+                new_name: new_device.name,
+                driver: new_device.driver,
+                changes,
+            });
+        }
+    }
+    diff.removed = old_devices.into_iter().map(|(key, _, device)| entry(key, &device)).collect();
+
+    let defaults = [
+        ("default_input", &old.default_input, &new.default_input),
+        ("default_output", &old.default_output, &new.default_output),
+    ];
+    for (field, old_name, new_name) in defaults {
+        // The same device under a renumbered card is still the same default
+        let old_key = old_name.as_deref().map(|name| default_key(old, name));
+        let new_key = new_name.as_deref().map(|name| default_key(new, name));
+        if old_key != new_key {
+            diff.defaults.push(FieldChange {
+                field: field.to_string(),
+                old: old_name.clone().map_or(Value::Null, Value::String),
+                new: new_name.clone().map_or(Value::Null, Value::String),
+            });
+        }
+    }
+
+    diff.differences = diff.added.len()
+        + diff.removed.len()
+        + diff.changed.iter().map(|d| d.changes.len()).sum::<usize>()
+        + diff.defaults.len();
+    diff
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => "none".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Print one block per added, removed or changed device and a summary line
+pub fn print_diff(diff: &SnapshotDiff) {
+    for device in &diff.added {
+        println!("┌─ Added: {} ({})", device.name, device.driver);
+        println!("└─ Key: {}", device.key);
+        println!();
+    }
+    for device in &diff.removed {
+        println!("┌─ Removed: {} ({})", device.name, device.driver);
+        println!("└─ Key: {}", device.key);
+        println!();
+    }
+    for device in diff.changed.iter().filter(|d| d.changes.is_empty()) {
+        println!("┌─ Renamed: {} → {} ({})", device.old_name, device.new_name, device.driver);
+        println!("└─ Key: {}", device.key);
+        println!();
+    }
+    for device in diff.changed.iter().filter(|d| !d.changes.is_empty()) {
+        if device.old_name == device.new_name {
+            println!("┌─ Changed: {} ({})", device.new_name, device.driver);
+        } else {
+            println!("┌─ Changed: {} → {} ({})", device.old_name, device.new_name, device.driver);
+        }
+        println!("├─ Key: {}", device.key);
+        for (i, change) in device.changes.iter().enumerate() {
+            let branch = if i + 1 == device.changes.len() { "└─" } else { "├─" };
+            println!("{} {}: {} → {}", branch, change.field, format_value(&change.old), format_value(&change.new));
+        }
+        println!();
+    }
+    for change in &diff.defaults {
+        println!("{}: {} → {}", change.field, format_value(&change.old), format_value(&change.new));
+    }
+    if !diff.defaults.is_empty() {
+        println!();
+    }
+
+    let renamed = diff.changed.iter().filter(|d| d.changes.is_empty()).count();
+    println!("{} added, {} removed, {} changed, {} renamed, {} unchanged ({} differences)",
+        diff.added.len(), diff.removed.len(), diff.changed.len() - renamed, renamed, diff.unchanged, diff.differences);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::CardIdentity;
+    use crate::CardInfo;
+
+    fn device(name: &str, channels: u32, rates: &[u32]) -> AudioDeviceInfo {
+        let mut device = AudioDeviceInfo::new(name.to_string(), "ALSA".to_string());
+        device.output_channels = channels;
+        device.supported_sample_rates = rates.to_vec();
+        device.update_device_type();
+        device
+    }
+
+    fn snapshot(devices: Vec<AudioDeviceInfo>, cards: &[(u32, &str)], default_output: Option<&str>) -> SystemAudioInfo {
+        SystemAudioInfo {
+            devices,
+            default_input: None,
+            default_input_source: None,
+            default_output: default_output.map(str::to_string),
+            default_output_source: None,
+            total_input_devices: 0,
+            total_output_devices: 0,
+            cards: cards.iter()
+                .map(|&(index, id)| CardInfo { index, id: id.to_string(), hda_codecs: Vec::new(), elds: Vec::new() })
+                .collect(),
+            backends: Vec::new(),
+        }
+    }
+
+    fn scarlett(name: &str) -> AudioDeviceInfo {
+        let mut device = device(name, 2, &[44100, 48000]);
+        device.hardware = Some(CardIdentity {
+            bus: Some("usb".to_string()),
+            vendor_id: Some("1235".to_string()),
+            product_id: Some("8210".to_string()),
+            serial: Some("Y8XXXXX0123456".to_string()),
+            ..Default::default()
+        });
+        device
+    }
+
+    #[test]
+    fn matches_renumbered_cards_and_reports_changes() {
+        // The USB interface moved from card 1 to card 2 and took its card id with it
+        let mut old = snapshot(
+            vec![device("hw:0,0", 2, &[44100, 48000]), scarlett("hw:1,0"), device("hw:0,3", 2, &[48000]), device("default", 2, &[])],
+            &[(0, "PCH"), (1, "USB")],
+            Some("hw:0,0"),
+        );
+        let mut busy = scarlett("hw:2,0");
+        busy.in_use = true;
+        busy.supported_sample_rates.push(96000);
+        let mut new = snapshot(
+            vec![device("hw:CARD=PCH,DEV=0", 2, &[44100, 48000]), busy, device("hw:1,0", 2, &[48000]), device("default", 2, &[])],
+            &[(0, "PCH"), (1, "Dock"), (2, "USB2")],
+            Some("hw:2,0"),
+        );
+        // The default input followed the interface to its new card number
+        old.default_input = Some("hw:1,0".to_string());
+        new.default_input = Some("hw:2,0".to_string());
+
+        let diff = diff_snapshots(&old, &new);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.added.iter().map(|d| d.key.as_str()).collect::<Vec<_>>(), ["ALSA/hw:Dock,0"]);
+        assert_eq!(diff.removed.iter().map(|d| d.key.as_str()).collect::<Vec<_>>(), ["ALSA/hw:PCH,3"]);
+
+        let [ref renamed, ref usb] = diff.changed[..] else { panic!("{:?}", diff.changed) };
+        assert_eq!(renamed.key, "ALSA/hw:PCH,0");
+        assert_eq!((renamed.old_name.as_str(), renamed.new_name.as_str()), ("hw:0,0", "hw:CARD=PCH,DEV=0"));
+        assert!(renamed.changes.is_empty());
+        assert_eq!(usb.key, "ALSA/hw:usb-1235:8210-Y8XXXXX0123456,0");
+        let fields: Vec<&str> = usb.changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["supported_sample_rates", "in_use"]);
+        assert_eq!(usb.changes[1], FieldChange { field: "in_use".to_string(), old: Value::Bool(false), new: Value::Bool(true) });
+
+        // The default output moved from the laptop's codec to the interface
+        assert_eq!(diff.defaults.iter().map(|c| c.field.as_str()).collect::<Vec<_>>(), ["default_output"]);
+        assert_eq!(diff.differences, 1 + 1 + 2 + 1);
+        assert_eq!(diff_snapshots(&new, &new).differences, 0);
+    }
+}
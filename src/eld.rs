@@ -0,0 +1,356 @@
+//! HDMI/DisplayPort ELD (EDID-Like Data) from `/proc/asound/cardN/eld#X.Y`
+//!
+//! The HDA HDMI driver writes one file per digital pin (X is the codec
+//! address, Y the pin index). When a sink is connected it describes the
+//! monitor and the audio formats it accepts as Short Audio Descriptors.
+//!
+//! The files do not say which PCM device they belong to. The codec dump
+//! lists an "ELD" control with its PCM device under each pin when PCMs are
+//! assigned statically; with dynamic assignment the control is read from the
+//! card's control interface and matched on the monitor identity instead.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::hda_codec::HdaCodec;
+
+/// One `eld#X.Y` file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Eld {
+    /// X: address of the HDMI codec
+    pub codec: u32,
+    /// Y: pin index within the codec
+    pub pin_index: u32,
+    /// PCM device this pin is routed to, when it could be determined
+    pub pcm_device: Option<u32>,
+    pub monitor_present: bool,
+    pub eld_valid: bool,
+    pub monitor_name: Option<String>,
+    /// "HDMI" or "DisplayPort"
+    pub connection_type: Option<String>,
+    pub eld_version: Option<String>,
+    pub edid_version: Option<String>,
+    pub manufacture_id: Option<u32>,
+    pub product_id: Option<u32>,
+    pub port_id: Option<String>,
+    pub support_hdcp: Option<bool>,
+    pub support_ai: Option<bool>,
+    pub audio_sync_delay_ms: Option<u32>,
+    /// Speaker allocation, e.g. ["FL/FR", "LFE", "FC"]
+    pub speakers: Vec<String>,
+    pub sads: Vec<ShortAudioDescriptor>,
+}
+
+/// One audio format the sink accepts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShortAudioDescriptor {
+    /// LPCM, AC-3, DTS, E-AC-3, ...
+    pub coding_type: String,
+    pub max_channels: Option<u32>,
+    pub rates: Vec<u32>,
+    /// Bit depths, LPCM only
+    pub bits: Vec<u32>,
+    /// Maximum bitrate in bit/s, compressed formats only
+    pub max_bitrate: Option<u32>,
+}
+
+/// Read every `eld#X.Y` file of a card directory and map them to PCM devices
+///
+/// Pins the codec dump does not map are matched through the ELD controls of
+/// `card`; pass `None` when the directory is not from the running system.
+pub fn read_elds(card_path: &Path, card: Option<u32>, codecs: &[HdaCodec]) -> Vec<Eld> {
+    let Ok(entries) = fs::read_dir(card_path) else { return Vec::new() };
+
+    let mut elds: Vec<Eld> = entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let (codec, pin_index) = name.to_str()?.strip_prefix("eld#")?.split_once('.')?;
+            let content = fs::read_to_string(entry.path()).ok()?;
+            let mut eld = parse_eld(&content);
+            eld.codec = codec.parse().ok()?;
+            eld.pin_index = pin_index.parse().ok()?;
+            Some(eld)
+        })
+        .collect();
+
+    elds.sort_by_key(|e| (e.codec, e.pin_index));
+    map_elds_from_codecs(&mut elds, codecs);
+    if let Some(card) = card.filter(|_| elds.iter().any(|e| e.monitor_present && e.pcm_device.is_none())) {
+        map_elds_from_controls(&mut elds, card);
+    }
+    elds
+}
+
+/// Parse the contents of an `eld#X.Y` file
+pub fn parse_eld(content: &str) -> Eld {
+    let mut eld = Eld::default();
+
+    for line in content.lines() {
+        // "monitor_name\t\tDELL U2720Q": key, then tabs, then the value
+        let Some((key, value)) = line.split_once(['\t', ' ']) else { continue };
+        let value = value.trim();
+
+        if let Some(rest) = key.strip_prefix("sad") {
+            // "sad0_coding_type", "sad1_rates", ...
+            let Some((index, field)) = rest.split_once('_') else { continue };
+            let Ok(index) = index.parse::<usize>() else { continue };
+            if eld.sads.len() <= index {
+                eld.sads.resize_with(index + 1, Default::default);
+            }
+            parse_sad_field(&mut eld.sads[index], field, value);
+            continue;
+        }
+
+        match key {
+            "monitor_present" => eld.monitor_present = value == "1",
+            "eld_valid" => eld.eld_valid = value == "1",
+            "monitor_name" => eld.monitor_name = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "connection_type" => eld.connection_type = Some(value.to_string()),
+            "eld_version" => eld.eld_version = Some(strip_raw(value).to_string()),
+            "edid_version" => eld.edid_version = Some(strip_raw(value).to_string()),
+            "manufacture_id" => eld.manufacture_id = parse_hex(value),
+            "product_id" => eld.product_id = parse_hex(value),
+            "port_id" => eld.port_id = Some(value.to_string()),
+            "support_hdcp" => eld.support_hdcp = Some(value == "1"),
+            "support_ai" => eld.support_ai = Some(value == "1"),
+            "audio_sync_delay" => eld.audio_sync_delay_ms = value.parse().ok(),
+            "speakers" => eld.speakers = strip_raw(value).split_whitespace().map(str::to_string).collect(),
+            _ => {}
+        }
+    }
+
+    eld
+}
+
+fn parse_sad_field(sad: &mut ShortAudioDescriptor, field: &str, value: &str) {
+    let numbers = |v: &str| strip_raw(v).split_whitespace().filter_map(|n| n.parse().ok()).collect();
+    match field {
+        "coding_type" => sad.coding_type = strip_raw(value).to_string(),
+        "channels" => sad.max_channels = value.parse().ok(),
+        "rates" => sad.rates = numbers(value),
+        "bits" => sad.bits = numbers(value),
+        "max_bitrate" => sad.max_bitrate = value.parse().ok(),
+        _ => {}
+    }
+}
+
+/// Drop the raw `"[0x1f] "` prefix the kernel prints before decoded values
+fn strip_raw(value: &str) -> &str {
+    match value.strip_prefix('[').and_then(|v| v.split_once(']')) {
+        Some((_, decoded)) => decoded.trim(),
+        None => value,
+    }
+}
+
+fn parse_hex(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim().strip_prefix("0x")?, 16).ok()
+}
+
+/// Map pins to PCMs through the "ELD" control listed under each pin widget
+///
+/// The driver numbers pins in node order, counting only pins with HDMI or
+/// DisplayPort capability.
+fn map_elds_from_codecs(elds: &mut [Eld], codecs: &[HdaCodec]) {
+    for codec in codecs {
+        let Some(address) = codec.address else { continue };
+        let digital_pins = codec.widgets.iter()
+            .filter(|w| w.widget_type == "Pin Complex")
+            .filter(|w| w.pin_cap_flags.iter().any(|f| f == "HDMI" || f == "DP"));
+
+        for (pin_index, pin) in digital_pins.enumerate() {
+            let Some(control) = pin.controls.iter().find(|c| c.name == "ELD") else { continue };
+            if let Some(eld) = elds.iter_mut().find(|e| e.codec == address && e.pin_index == pin_index as u32) {
+                eld.pcm_device = Some(control.device);
+            }
+        }
+    }
+}
+
+/// Map connected monitors by reading each PCM's "ELD" control and matching
+/// the monitor identity it contains against the files
+#[cfg(target_os = "linux")]
+fn map_elds_from_controls(elds: &mut [Eld], card: u32) {
+    use alsa::ctl::{Ctl, DeviceIter, ElemId, ElemIface, ElemType, ElemValue};
+    use std::ffi::CString;
+
+    let Ok(ctl) = Ctl::new(&format!("hw:{}", card), false) else { return };
+    let Ok(name) = CString::new("ELD") else { return };
+
+    for device in DeviceIter::new(&ctl) {
+        let Ok(device) = u32::try_from(device) else { continue };
+        if elds.iter().any(|e| e.pcm_device == Some(device)) {
+            continue;
+        }
+
+        let mut id = ElemId::new(ElemIface::PCM);
+        id.set_name(&name);
+        id.set_device(device);
+        let Ok(mut value) = ElemValue::new(ElemType::Bytes) else { continue };
+        value.set_id(&id);
+        if ctl.elem_read(&mut value).is_err() {
+            continue;
+        }
+        let Some((manufacture_id, product_id, monitor_name)) = value.get_bytes().and_then(decode_eld_identity) else { continue };
+
+        let matching = elds.iter_mut().find(|e| {
+            e.monitor_present && e.pcm_device.is_none() &&
+            e.manufacture_id == Some(manufacture_id) &&
+            e.product_id == Some(product_id) &&
+            e.monitor_name.as_deref().unwrap_or("") == monitor_name
+        });
+        if let Some(eld) = matching {
+            eld.pcm_device = Some(device);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn map_elds_from_controls(_elds: &mut [Eld], _card: u32) {
+    // ALSA is Linux-specific
+}
+
+/// Manufacturer, product and monitor name from a raw ELD buffer
+///
+/// Baseline block layout (HDA spec 7.3.3.34): byte 4 holds the monitor name
+/// length, bytes 16-19 the little-endian manufacturer and product codes and
+/// the name starts at byte 20. An all-zero buffer means no sink.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn decode_eld_identity(raw: &[u8]) -> Option<(u32, u32, String)> {
+    if raw.len() < 20 || raw[2] == 0 {
+        return None;
+    }
+    let name_len = (raw[4] & 0x1f) as usize;
+    let manufacture_id = u16::from_le_bytes([raw[16], raw[17]]) as u32;
+    let product_id = u16::from_le_bytes([raw[18], raw[19]]) as u32;
+    let name = raw.get(20..20 + name_len)?;
+    Some((manufacture_id, product_id, String::from_utf8_lossy(name).trim_end_matches('\0').to_string()))
+}
+
+impl Eld {
+    /// One-line description, e.g. "DELL U2720Q, LPCM 2ch 32-48k"
+    pub fn summary(&self) -> String {
+        if !self.monitor_present {
+            return "no monitor connected".to_string();
+        }
+
+        let mut parts = vec![self.monitor_name.clone().unwrap_or_else(|| "unnamed monitor".to_string())];
+        parts.extend(self.sads.iter().map(|sad| {
+            let khz = |rate: u32| match rate {
+                44100 => "44.1".to_string(),
+                88200 => "88.2".to_string(),
+                176400 => "176.4".to_string(),
+                rate => (rate / 1000).to_string(),
+            };
+            let rates = match (sad.rates.iter().min(), sad.rates.iter().max()) {
+                (Some(&min), Some(&max)) if min != max => format!(" {}-{}k", khz(min), khz(max)),
+                (Some(&rate), _) => format!(" {}k", khz(rate)),
+                _ => String::new(),
+            };
+            let channels = sad.max_channels.map_or(String::new(), |c| format!(" {}ch", c));
+            format!("{}{}{}", sad.coding_type, channels, rates)
+        }));
+        parts.join(", ")
+    }
+}
+
+/// Print the ELDs of a card as part of a verbose listing
+pub fn print_elds(card: u32, elds: &[Eld]) {
+    println!("┌─ Card {} HDMI/DisplayPort sinks", card);
+    for (i, eld) in elds.iter().enumerate() {
+        let last = i + 1 == elds.len();
+        let (branch, indent) = if last { ("└─", "   ") } else { ("├─", "│  ") };
+        let pcm = eld.pcm_device.map_or("PCM unknown".to_string(), |d| format!("hw:{},{}", card, d));
+        println!("{} eld#{}.{} ({}): {}", branch, eld.codec, eld.pin_index, pcm, eld.summary());
+
+        if !eld.monitor_present {
+            continue;
+        }
+        let mut lines = Vec::new();
+        if let Some(ref connection) = eld.connection_type {
+            lines.push(format!("Connection: {}", connection));
+        }
+        if !eld.speakers.is_empty() {
+            lines.push(format!("Speakers: {}", eld.speakers.join(" ")));
+        }
+        for sad in &eld.sads {
+            let mut line = format!("{}: up to {} channels, {:?} Hz",
+                sad.coding_type, sad.max_channels.unwrap_or(0), sad.rates);
+            if !sad.bits.is_empty() {
+                line.push_str(&format!(", {:?} bit", sad.bits));
+            }
+            if let Some(bitrate) = sad.max_bitrate {
+                line.push_str(&format!(", max {} kbit/s", bitrate / 1000));
+            }
+            lines.push(line);
+        }
+        for (j, line) in lines.iter().enumerate() {
+            let branch = if j + 1 == lines.len() { "└─" } else { "├─" };
+            println!("{}{} {}", indent, branch, line);
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hda_codec::read_hda_codecs;
+
+    fn fixture_elds() -> Vec<Eld> {
+        let card = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysroot/hdmi/proc/asound/card0");
+        let codecs = read_hda_codecs(&card);
+        // Card 99 does not exist, so only the codec dump mapping applies
+        read_elds(&card, Some(99), &codecs)
+    }
+
+    #[test]
+    fn parses_monitor_and_sads() {
+        let elds = fixture_elds();
+        assert_eq!(elds.len(), 3);
+
+        let dell = &elds[0];
+        assert_eq!((dell.codec, dell.pin_index), (2, 0));
+        assert!(dell.monitor_present && dell.eld_valid);
+        assert_eq!(dell.monitor_name.as_deref(), Some("DELL U2720Q"));
+        assert_eq!(dell.connection_type.as_deref(), Some("DisplayPort"));
+        assert_eq!(dell.manufacture_id, Some(0xac10));
+        assert_eq!(dell.speakers, ["FL/FR"]);
+        assert_eq!(dell.sads, [ShortAudioDescriptor {
+            coding_type: "LPCM".to_string(),
+            max_channels: Some(2),
+            rates: vec![32000, 44100, 48000],
+            bits: vec![16, 20, 24],
+            max_bitrate: None,
+        }]);
+        assert_eq!(dell.summary(), "DELL U2720Q, LPCM 2ch 32-48k");
+
+        let tv = &elds[1];
+        assert_eq!(tv.speakers, ["FL/FR", "LFE", "FC", "RL/RR", "RC"]);
+        assert_eq!(tv.sads[1].coding_type, "AC-3");
+        assert_eq!(tv.sads[1].max_bitrate, Some(640000));
+        assert_eq!(tv.summary(), "SONY TV, LPCM 8ch 32-192k, AC-3 6ch 32-48k");
+
+        assert!(!elds[2].monitor_present);
+        assert_eq!(elds[2].summary(), "no monitor connected");
+    }
+
+    #[test]
+    fn maps_pins_to_pcm_devices_through_codec_controls() {
+        let devices: Vec<Option<u32>> = fixture_elds().iter().map(|e| e.pcm_device).collect();
+        assert_eq!(devices, [Some(3), Some(7), Some(8)]);
+    }
+
+    #[test]
+    fn decodes_identity_from_raw_eld() {
+        let mut raw = vec![0u8; 32];
+        raw[0] = 0x10;
+        raw[2] = 0x07;
+        raw[4] = 0x0b;
+        raw[16..20].copy_from_slice(&[0x10, 0xac, 0xf8, 0xa0]);
+        raw[20..31].copy_from_slice(b"DELL U2720Q");
+
+        assert_eq!(decode_eld_identity(&raw), Some((0xac10, 0xa0f8, "DELL U2720Q".to_string())));
+        assert_eq!(decode_eld_identity(&[0u8; 32]), None);
+    }
+}
@@ -0,0 +1,434 @@
+//! Parser for HDA codec dumps in `/proc/asound/cardN/codec#M`
+//!
+//! snd-hda-intel writes one file per codec on the HD Audio link. It lists the
+//! codec's IDs and function groups followed by every widget node: its type,
+//! capability bits, amplifier ranges, connection list and, for pin
+//! complexes, the BIOS default configuration that says what jack or speaker
+//! the pin is wired to.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// One codec on the HDA link
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HdaCodec {
+    /// M in `codec#M`
+    pub index: u32,
+    /// "Codec:" line, e.g. "Realtek ALC3246"
+    pub name: String,
+    pub address: Option<u32>,
+    pub vendor_id: Option<u32>,
+    pub subsystem_id: Option<u32>,
+    pub revision_id: Option<u32>,
+    pub function_groups: Vec<HdaFunctionGroup>,
+    pub widgets: Vec<HdaWidget>,
+}
+
+/// An audio or modem function group
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HdaFunctionGroup {
+    /// "AFG" (audio) or "MFG" (modem)
+    pub kind: String,
+    pub function_id: u32,
+    pub unsolicited: Option<bool>,
+}
+
+/// One widget node
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HdaWidget {
+    pub nid: u32,
+    /// Audio Output, Audio Input, Audio Mixer, Audio Selector, Pin Complex, ...
+    pub widget_type: String,
+    /// Raw audio widget capabilities
+    pub wcaps: u32,
+    /// Decoded capability words, e.g. ["Stereo", "Amp-Out"]
+    pub capabilities: Vec<String>,
+    /// ALSA controls created on this node
+    pub controls: Vec<HdaControl>,
+    pub amp_in: Option<HdaAmpCaps>,
+    pub amp_out: Option<HdaAmpCaps>,
+    /// Node IDs this widget can take input from
+    pub connections: Vec<u32>,
+    /// Currently selected connection for selectors and pins
+    pub selected_connection: Option<u32>,
+    pub pin_caps: Option<u32>,
+    /// Decoded pin capabilities, e.g. ["OUT", "HP", "EAPD", "Detect"]
+    pub pin_cap_flags: Vec<String>,
+    pub pin_default: Option<HdaPinDefault>,
+    /// Device entries of a DisplayPort MST capable pin ("Devices: N")
+    pub mst_devices: Option<u32>,
+}
+
+/// An ALSA control attached to a widget
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HdaControl {
+    pub name: String,
+    pub index: u32,
+    /// PCM device number for PCM-interface controls such as "ELD"
+    pub device: u32,
+}
+
+/// Amplifier range of one direction
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HdaAmpCaps {
+    /// Step at which the gain is 0 dB
+    pub offset: u32,
+    pub num_steps: u32,
+    /// Step size in 0.25 dB units, minus one
+    pub step_size: u32,
+    pub mute: bool,
+    pub min_db: f32,
+    pub max_db: f32,
+}
+
+/// Decoded pin default configuration (what the BIOS says the pin is wired to)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HdaPinDefault {
+    pub raw: u32,
+    /// Jack, No Connection, Fixed or Both
+    pub connectivity: String,
+    /// e.g. "External Rear", "Internal N/A"
+    pub location: String,
+    /// Line Out, Speaker, HP Out, Mic, ...
+    pub device: String,
+    /// 1/8", RCA, Optical, ...
+    pub connection_type: String,
+    pub color: String,
+    /// Jack detection is not available (misc bit 0)
+    pub no_presence_detect: bool,
+    pub association: u32,
+    pub sequence: u32,
+}
+
+/// Read every `codec#M` file of a card directory, ordered by codec index
+pub fn read_hda_codecs(card_path: &Path) -> Vec<HdaCodec> {
+    let Ok(entries) = fs::read_dir(card_path) else { return Vec::new() };
+
+    let mut codecs: Vec<HdaCodec> = entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let index = name.to_str()?.strip_prefix("codec#")?.parse().ok()?;
+            let content = fs::read_to_string(entry.path()).ok()?;
+            let mut codec = parse_hda_codec(&content);
+            codec.index = index;
+            Some(codec)
+        })
+        .collect();
+
+    codecs.sort_by_key(|c| c.index);
+    codecs
+}
+
+/// Parse the contents of a `codec#M` file
+pub fn parse_hda_codec(content: &str) -> HdaCodec {
+    let mut codec = HdaCodec::default();
+    // The line after "Connection: N" lists the node IDs
+    let mut expect_connections = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        if expect_connections {
+            expect_connections = false;
+            if let Some(widget) = codec.widgets.last_mut() {
+                for token in trimmed.split_whitespace() {
+                    let selected = token.ends_with('*');
+                    if let Some(nid) = parse_hex(token.trim_end_matches('*')) {
+                        widget.connections.push(nid);
+                        if selected {
+                            widget.selected_connection = Some(nid);
+                        }
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Codec-level lines are not indented
+        if !line.starts_with(' ') {
+            if let Some(rest) = trimmed.strip_prefix("Node ") {
+                codec.widgets.push(parse_node_header(rest));
+            } else if let Some((key, value)) = trimmed.split_once(':') {
+                parse_codec_field(&mut codec, key.trim(), value.trim());
+            }
+            continue;
+        }
+
+        let Some(widget) = codec.widgets.last_mut() else { continue };
+
+        if let Some(rest) = trimmed.strip_prefix("Control: name=\"") {
+            // name="Headphone Playback Volume", index=0, device=0
+            if let Some((name, fields)) = rest.split_once('"') {
+                let field = |key: &str| fields.split(',')
+                    .find_map(|f| f.trim().strip_prefix(key))
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0);
+                widget.controls.push(HdaControl {
+                    name: name.to_string(),
+                    index: field("index="),
+                    device: field("device="),
+                });
+            }
+        } else if let Some(count) = trimmed.strip_prefix("Devices:") {
+            widget.mst_devices = count.trim().parse().ok();
+        } else if let Some(caps) = trimmed.strip_prefix("Amp-In caps:") {
+            widget.amp_in = parse_amp_caps(caps);
+        } else if let Some(caps) = trimmed.strip_prefix("Amp-Out caps:") {
+            widget.amp_out = parse_amp_caps(caps);
+        } else if trimmed.starts_with("Connection:") {
+            expect_connections = true;
+        } else if let Some(rest) = trimmed.strip_prefix("Pincap ") {
+            // "Pincap 0x0001001c: OUT HP EAPD Detect"
+            let (raw, flags) = rest.split_once(':').unwrap_or((rest, ""));
+            widget.pin_caps = parse_hex(raw.trim());
+            widget.pin_cap_flags = flags.split_whitespace().map(str::to_string).collect();
+        } else if let Some(rest) = trimmed.strip_prefix("Pin Default ") {
+            // Decode the raw value ourselves; the text form abbreviates
+            widget.pin_default = rest.split(':').next()
+                .and_then(|raw| parse_hex(raw.trim()))
+                .map(decode_pin_default);
+        }
+    }
+
+    codec
+}
+
+fn parse_codec_field(codec: &mut HdaCodec, key: &str, value: &str) {
+    match key {
+        "Codec" => codec.name = value.to_string(),
+        "Address" => codec.address = value.parse().ok(),
+        "Vendor Id" => codec.vendor_id = parse_hex(value),
+        "Subsystem Id" => codec.subsystem_id = parse_hex(value),
+        "Revision Id" => codec.revision_id = parse_hex(value),
+        "AFG Function Id" | "MFG Function Id" => {
+            // "0x1 (unsol 1)"
+            let mut parts = value.split_whitespace();
+            if let Some(function_id) = parts.next().and_then(parse_hex) {
+                codec.function_groups.push(HdaFunctionGroup {
+                    kind: key[..3].to_string(),
+                    function_id,
+                    unsolicited: value.split_once("unsol ")
+                        .map(|(_, flag)| flag.trim_end_matches(')') == "1"),
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parse "0x02 [Audio Output] wcaps 0x41d: Stereo Amp-Out"
+fn parse_node_header(rest: &str) -> HdaWidget {
+    let mut widget = HdaWidget::default();
+    let (nid, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    widget.nid = parse_hex(nid).unwrap_or(0);
+
+    if let Some((widget_type, rest)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+        widget.widget_type = widget_type.to_string();
+        if let Some((wcaps, capabilities)) = rest.trim().strip_prefix("wcaps ").and_then(|r| r.split_once(':')) {
+            widget.wcaps = parse_hex(wcaps.trim()).unwrap_or(0);
+            widget.capabilities = capabilities.split_whitespace().map(str::to_string).collect();
+        }
+    }
+
+    widget
+}
+
+/// Parse " ofs=0x57, nsteps=0x57, stepsize=0x02, mute=0"; "N/A" means no amp
+fn parse_amp_caps(caps: &str) -> Option<HdaAmpCaps> {
+    let mut amp = HdaAmpCaps::default();
+    let mut seen = false;
+
+    for field in caps.split(',') {
+        let Some((key, value)) = field.trim().split_once('=') else { continue };
+        let value = parse_hex(value).or_else(|| value.parse().ok());
+        seen = true;
+        match key {
+            "ofs" => amp.offset = value.unwrap_or(0),
+            "nsteps" => amp.num_steps = value.unwrap_or(0),
+            "stepsize" => amp.step_size = value.unwrap_or(0),
+            "mute" => amp.mute = value == Some(1),
+            _ => {}
+        }
+    }
+
+    if !seen {
+        return None;
+    }
+
+    // Each step is (stepsize + 1) * 0.25 dB and step `ofs` is 0 dB
+    let step_db = (amp.step_size + 1) as f32 * 0.25;
+    amp.min_db = 0.0 - amp.offset as f32 * step_db;
+    amp.max_db = (amp.num_steps as f32 - amp.offset as f32) * step_db;
+    Some(amp)
+}
+
+fn parse_hex(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim().strip_prefix("0x")?, 16).ok()
+}
+
+/// Decode a pin configuration default register (HDA spec section 7.3.3.31)
+pub fn decode_pin_default(raw: u32) -> HdaPinDefault {
+    const CONNECTIVITY: [&str; 4] = ["Jack", "No Connection", "Fixed", "Both"];
+    const GROSS_LOCATION: [&str; 4] = ["External", "Internal", "Separate Chassis", "Other"];
+    const GEOMETRIC_LOCATION: [&str; 7] = ["N/A", "Rear", "Front", "Left", "Right", "Top", "Bottom"];
+    const DEVICE: [&str; 16] = [
+        "Line Out", "Speaker", "HP Out", "CD", "SPDIF Out", "Digital Out", "Modem Line", "Modem Handset",
+        "Line In", "Aux", "Mic", "Telephony", "SPDIF In", "Digital In", "Reserved", "Other",
+    ];
+    const CONNECTION_TYPE: [&str; 16] = [
+        "Unknown", "1/8\"", "1/4\"", "ATAPI", "RCA", "Optical", "Other Digital", "Other Analog",
+        "Multichannel Analog (DIN)", "XLR/Professional", "RJ-11 (Modem)", "Combination",
+        "Reserved", "Reserved", "Reserved", "Other",
+    ];
+    const COLOR: [&str; 16] = [
+        "Unknown", "Black", "Grey", "Blue", "Green", "Red", "Orange", "Yellow",
+        "Purple", "Pink", "Reserved", "Reserved", "Reserved", "Reserved", "White", "Other",
+    ];
+
+    let location_bits = (raw >> 24) & 0x3f;
+    let gross = (location_bits >> 4) as usize;
+    let geometric = (location_bits & 0xf) as usize;
+    // Values 7-9 are special locations whose meaning depends on the gross location
+    let location = match location_bits {
+        0x07 => "External Rear Panel".to_string(),
+        0x08 => "External Drive Bay".to_string(),
+        0x17 => "Internal Riser".to_string(),
+        0x18 => "Internal Digital Display".to_string(),
+        0x19 => "Internal ATAPI".to_string(),
+        0x37 => "Other Mobile Lid (Inside)".to_string(),
+        0x38 => "Other Mobile Lid (Outside)".to_string(),
+        _ => format!("{} {}", GROSS_LOCATION[gross], GEOMETRIC_LOCATION.get(geometric).unwrap_or(&"Reserved")),
+    };
+
+    HdaPinDefault {
+        raw,
+        connectivity: CONNECTIVITY[(raw >> 30) as usize].to_string(),
+        location,
+        device: DEVICE[((raw >> 20) & 0xf) as usize].to_string(),
+        connection_type: CONNECTION_TYPE[((raw >> 16) & 0xf) as usize].to_string(),
+        color: COLOR[((raw >> 12) & 0xf) as usize].to_string(),
+        no_presence_detect: (raw >> 8) & 0x1 != 0,
+        association: (raw >> 4) & 0xf,
+        sequence: raw & 0xf,
+    }
+}
+
+/// Print a codec as part of a verbose card listing
+pub fn print_hda_codec(card: u32, codec: &HdaCodec) {
+    let hex = |value: Option<u32>| value.map_or("?".to_string(), |v| format!("0x{:08x}", v));
+    println!("┌─ Card {} Codec #{}: {}", card, codec.index, codec.name);
+    println!("├─ Vendor ID: {}, Subsystem ID: {}, Revision: {}",
+        hex(codec.vendor_id), hex(codec.subsystem_id), hex(codec.revision_id));
+
+    let groups: Vec<String> = codec.function_groups.iter()
+        .map(|g| format!("{} 0x{:x}", g.kind, g.function_id))
+        .collect();
+    if !groups.is_empty() {
+        println!("├─ Function Groups: {}", groups.join(", "));
+    }
+
+    println!("├─ Widgets:");
+    for (i, widget) in codec.widgets.iter().enumerate() {
+        let branch = if i + 1 == codec.widgets.len() { "└─" } else { "├─" };
+        let mut details = vec![widget.capabilities.join(" ")];
+
+        for (label, amp) in [("in", &widget.amp_in), ("out", &widget.amp_out)] {
+            if let Some(amp) = amp.as_ref().filter(|a| a.num_steps > 0) {
+                details.push(format!("amp-{} {:.2}..{:.2} dB", label, amp.min_db, amp.max_db));
+            }
+        }
+        if let Some(ref pin) = widget.pin_default {
+            details.push(format!("{} at {}, {}, {} ({})",
+                pin.device, pin.location, pin.connection_type, pin.color, pin.connectivity));
+        }
+        if !widget.connections.is_empty() {
+            let connections: Vec<String> = widget.connections.iter()
+                .map(|&nid| if widget.selected_connection == Some(nid) { format!("0x{:02x}*", nid) } else { format!("0x{:02x}", nid) })
+                .collect();
+            details.push(format!("← {}", connections.join(" ")));
+        }
+
+        println!("│  {} 0x{:02x} {}: {}", branch, widget.nid, widget.widget_type,
+            details.into_iter().filter(|d| !d.is_empty()).collect::<Vec<_>>().join("; "));
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_codec() -> HdaCodec {
+        let card = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysroot/hda/proc/asound/card0");
+        read_hda_codecs(&card).into_iter().next().expect("codec#0 in fixture")
+    }
+
+    fn widget(codec: &HdaCodec, nid: u32) -> &HdaWidget {
+        codec.widgets.iter().find(|w| w.nid == nid).expect("widget in fixture")
+    }
+
+    #[test]
+    fn parses_codec_header() {
+        let codec = fixture_codec();
+
+        assert_eq!(codec.index, 0);
+        assert_eq!(codec.name, "Realtek ALC3246");
+        assert_eq!(codec.vendor_id, Some(0x10ec0256));
+        assert_eq!(codec.subsystem_id, Some(0x10280869));
+        assert_eq!(codec.revision_id, Some(0x100002));
+        assert_eq!(codec.function_groups.len(), 1);
+        assert_eq!(codec.function_groups[0].kind, "AFG");
+        assert_eq!(codec.function_groups[0].unsolicited, Some(true));
+        assert_eq!(codec.widgets.len(), 11);
+    }
+
+    #[test]
+    fn parses_widgets_and_amps() {
+        let codec = fixture_codec();
+
+        let dac = widget(&codec, 0x02);
+        assert_eq!(dac.widget_type, "Audio Output");
+        assert_eq!(dac.wcaps, 0x41d);
+        assert_eq!(dac.capabilities, ["Stereo", "Amp-Out"]);
+        assert_eq!(dac.controls, [HdaControl { name: "Headphone Playback Volume".to_string(), index: 0, device: 0 }]);
+        let amp = dac.amp_out.as_ref().expect("amp-out caps");
+        assert_eq!((amp.offset, amp.num_steps, amp.step_size, amp.mute), (0x57, 0x57, 0x02, false));
+        assert_eq!((amp.min_db, amp.max_db), (-65.25, 0.0));
+
+        let mixer = widget(&codec, 0x23);
+        assert_eq!(mixer.connections, [0x19, 0x1a, 0x12]);
+        assert_eq!(mixer.selected_connection, None);
+
+        let headphone = widget(&codec, 0x21);
+        assert_eq!(headphone.connections, [0x02, 0x03]);
+        assert_eq!(headphone.selected_connection, Some(0x02));
+        assert_eq!(headphone.pin_caps, Some(0x0001001c));
+        assert_eq!(headphone.pin_cap_flags, ["OUT", "HP", "EAPD", "Detect"]);
+    }
+
+    #[test]
+    fn decodes_pin_defaults() {
+        let codec = fixture_codec();
+
+        let headphone = widget(&codec, 0x21).pin_default.as_ref().expect("pin default");
+        assert_eq!(headphone.connectivity, "Jack");
+        assert_eq!(headphone.location, "External Front");
+        assert_eq!(headphone.device, "HP Out");
+        assert_eq!(headphone.connection_type, "1/8\"");
+        assert_eq!(headphone.color, "Black");
+        assert!(!headphone.no_presence_detect);
+        assert_eq!((headphone.association, headphone.sequence), (2, 0));
+
+        let mic = widget(&codec, 0x12).pin_default.as_ref().expect("pin default");
+        assert_eq!(mic.connectivity, "Fixed");
+        assert_eq!(mic.location, "Internal N/A");
+        assert_eq!(mic.device, "Mic");
+        assert_eq!(mic.connection_type, "Other Digital");
+        assert!(mic.no_presence_detect);
+
+        let unused = widget(&codec, 0x19).pin_default.as_ref().expect("pin default");
+        assert_eq!(unused.connectivity, "No Connection");
+        assert_eq!(unused.location, "External Rear");
+    }
+}
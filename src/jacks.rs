@@ -0,0 +1,188 @@
+//! Jack plug state from ALSA control elements
+//!
+//! Drivers with jack detection expose one boolean card control per connector,
+//! named `"<connector> Jack"` ("Headphone Jack", "Mic Jack",
+//! "HDMI/DP,pcm=3 Jack"). Reading them through the control interface never
+//! touches a PCM, so it is safe while audio is playing.
+
+use serde::{Deserialize, Serialize};
+
+/// One "... Jack" control
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JackState {
+    pub card: u32,
+    /// Full control name, e.g. "Front Headphone Jack"
+    pub control: String,
+    /// Connector name without the " Jack" suffix
+    pub connector: String,
+    /// Control index, distinguishing several connectors with the same name
+    pub index: u32,
+    pub plugged: bool,
+    /// Phantom jacks belong to fixed connectors (internal speakers, built-in
+    /// mics) and always report plugged
+    pub phantom: bool,
+    /// PCM device the connector belongs to; explicit for HDMI ("pcm=3"),
+    /// otherwise the card's primary analog device 0
+    pub pcm_device: Option<u32>,
+    /// "playback" or "capture", inferred from the connector name
+    pub direction: Option<String>,
+}
+
+/// Read the jacks of every card, optionally restricted to one
+#[cfg(target_os = "linux")]
+pub fn read_all_jacks(card_filter: Option<u32>) -> Vec<JackState> {
+    let mut jacks = Vec::new();
+
+    for card in alsa::card::Iter::new().flatten() {
+        let index = card.get_index() as u32;
+        if card_filter.is_some_and(|c| c != index) {
+            continue;
+        }
+        match read_card_jacks(index) {
+            Ok(card_jacks) => jacks.extend(card_jacks),
+            Err(e) => eprintln!("Warning: Failed to read jack controls of card {}: {}", index, e),
+        }
+    }
+
+    jacks
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_all_jacks(_card_filter: Option<u32>) -> Vec<JackState> {
+    Vec::new() // ALSA is Linux-specific
+}
+
+/// Read every "... Jack" boolean control of one card
+#[cfg(target_os = "linux")]
+pub fn read_card_jacks(card: u32) -> anyhow::Result<Vec<JackState>> {
+    use alsa::ctl::{ElemIface, ElemType};
+    use alsa::hctl::HCtl;
+
+    let hctl = HCtl::new(&format!("hw:{}", card), false)?;
+    hctl.load()?;
+
+    let mut jacks = Vec::new();
+    for elem in hctl.elem_iter() {
+        let Ok(id) = elem.get_id() else { continue };
+        if id.get_interface() != ElemIface::Card {
+            continue;
+        }
+        let Ok(control) = id.get_name().map(str::to_string) else { continue };
+        let Some(connector) = control.strip_suffix(" Jack") else { continue };
+        if elem.info().map(|info| info.get_type()).ok() != Some(ElemType::Boolean) {
+            continue;
+        }
+        let Some(plugged) = elem.read().ok().and_then(|value| value.get_boolean(0)) else { continue };
+
+        jacks.push(jack_state(card, &control, connector, id.get_index(), plugged));
+    }
+
+    Ok(jacks)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_card_jacks(_card: u32) -> anyhow::Result<Vec<JackState>> {
+    Ok(Vec::new()) // ALSA is Linux-specific
+}
+
+/// Classify a jack control by its name
+pub fn jack_state(card: u32, control: &str, connector: &str, index: u32, plugged: bool) -> JackState {
+    let phantom = connector.ends_with(" Phantom");
+    let connector = connector.trim_end_matches(" Phantom");
+
+    // "HDMI/DP,pcm=3" names its PCM; analog jacks belong to device 0
+    let explicit_pcm = connector.split_once(",pcm=").and_then(|(_, pcm)| pcm.parse().ok());
+    let lower = connector.to_lowercase();
+    // Input markers come first: "Headphone Mic" (combo jacks) and "SPDIF In" are capture
+    let input = ["mic", "capture", "input"].iter().any(|k| lower.contains(k))
+        || lower.split_whitespace().any(|word| word == "in");
+    let direction = if explicit_pcm.is_some() {
+        Some("playback")
+    } else if input {
+        Some("capture")
+    } else if ["headphone", "line out", "speaker", "spdif", "hdmi"].iter().any(|k| lower.contains(k)) {
+        Some("playback")
+    } else if lower.contains("line") {
+        Some("capture")
+    } else {
+        None
+    };
+
+    JackState {
+        card,
+        control: control.to_string(),
+        connector: connector.to_string(),
+        index,
+        plugged,
+        phantom,
+        pcm_device: explicit_pcm.or(Some(0)),
+        direction: direction.map(str::to_string),
+    }
+}
+
+impl JackState {
+    pub fn state_label(&self) -> &'static str {
+        match (self.phantom, self.plugged) {
+            (true, _) => "fixed",
+            (false, true) => "plugged",
+            (false, false) => "unplugged",
+        }
+    }
+}
+
+/// Print jacks grouped by card
+pub fn print_jacks(jacks: &[JackState]) {
+    if jacks.is_empty() {
+        println!("No jack detection controls found.");
+        return;
+    }
+
+    let mut cards: Vec<u32> = jacks.iter().map(|j| j.card).collect();
+    cards.dedup();
+
+    for card in cards {
+        let card_jacks: Vec<&JackState> = jacks.iter().filter(|j| j.card == card).collect();
+        println!("┌─ Card {}", card);
+        for (i, jack) in card_jacks.iter().enumerate() {
+            let branch = if i + 1 == card_jacks.len() { "└─" } else { "├─" };
+            let pcm = jack.pcm_device.map_or(String::new(), |d| format!("hw:{},{}", card, d));
+            let connector = if jack.index > 0 { format!("{} #{}", jack.connector, jack.index) } else { jack.connector.clone() };
+            println!("{} {:<28} {:<10} {} {}",
+                branch, connector, jack.state_label(), pcm, jack.direction.as_deref().unwrap_or(""));
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_jack_controls() {
+        let hdmi = jack_state(0, "HDMI/DP,pcm=7 Jack", "HDMI/DP,pcm=7", 0, true);
+        assert_eq!((hdmi.pcm_device, hdmi.direction.as_deref()), (Some(7), Some("playback")));
+
+        let headphone = jack_state(0, "Front Headphone Jack", "Front Headphone", 0, false);
+        assert_eq!((headphone.pcm_device, headphone.direction.as_deref()), (Some(0), Some("playback")));
+        assert_eq!(headphone.state_label(), "unplugged");
+
+        let line_out = jack_state(0, "Line Out Jack", "Line Out", 1, true);
+        assert_eq!(line_out.direction.as_deref(), Some("playback"));
+
+        let combo = jack_state(0, "Headphone Mic Jack", "Headphone Mic", 0, true);
+        assert_eq!(combo.direction.as_deref(), Some("capture"));
+        let spdif_in = jack_state(0, "SPDIF In Jack", "SPDIF In", 0, false);
+        assert_eq!(spdif_in.direction.as_deref(), Some("capture"));
+        let spdif_out = jack_state(0, "SPDIF Jack", "SPDIF", 0, true);
+        assert_eq!(spdif_out.direction.as_deref(), Some("playback"));
+        let line_in = jack_state(0, "Line Jack", "Line", 0, false);
+        assert_eq!(line_in.direction.as_deref(), Some("capture"));
+
+        let mic = jack_state(0, "Internal Mic Phantom Jack", "Internal Mic Phantom", 0, true);
+        assert_eq!(mic.connector, "Internal Mic");
+        assert_eq!(mic.direction.as_deref(), Some("capture"));
+        assert!(mic.phantom);
+        assert_eq!(mic.state_label(), "fixed");
+    }
+}
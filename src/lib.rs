@@ -0,0 +1,41 @@
+//! Audio Interrogator: discover Linux audio devices and their capabilities
+//!
+//! The `audio-interrogator` binary is a thin command line interface over this
+//! crate. Rust programs can call the same interrogation directly:
+//!
+//! ```no_run
+//! let info = audio_interrogator::get_system_audio_info(false)?;
+//! for device in info.output_devices() {
+//!     println!("{}: {} channels", device.name, device.output_channels);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The CPAL and ALSA backends live in [`devices`]; the other modules each read
+//! one kind of kernel or configuration state and are usable on their own.
+
+pub mod devices;
+
+pub mod bundle;
+pub mod chmap;
+pub mod controls;
+pub mod defaults;
+pub mod diff;
+pub mod eld;
+pub mod hda_codec;
+pub mod jacks;
+pub mod matrix;
+pub mod midi;
+pub mod mixer;
+pub mod owners;
+pub mod pcm_status;
+pub mod sysfs;
+pub mod sysroot;
+pub mod ucm;
+pub mod usb_stream;
+
+pub use devices::{
+    get_system_audio_info, AudioDeviceInfo, CardInfo, HwCapabilities, StreamCapabilities,
+    SystemAudioInfo, ValueSource,
+};
+pub use sysroot::SysRoot;
@@ -1,313 +1,122 @@
-use std::collections::{HashSet, HashMap};
+use std::path::Path;
 use anyhow::Result;
 use clap::{Arg, Command};
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize, Deserialize)]
-struct AudioDeviceInfo {
-    name: String,
-    device_type: String,
-    input_channels: u32,
-    output_channels: u32,
-    supported_sample_rates: Vec<u32>,
-    supported_buffer_sizes: Vec<u32>,
-    default_sample_rate: u32,
-    default_buffer_size: u32,
-    driver: String,
-}
 
-impl AudioDeviceInfo {
-    fn new(name: String, driver: String) -> Self {
-        Self {
-            name,
-            device_type: "Unknown".to_string(),
-            input_channels: 0,
-            output_channels: 0,
-            supported_sample_rates: Vec::new(),
-            supported_buffer_sizes: vec![64, 128, 256, 512, 1024, 2048, 4096],
-            default_sample_rate: 44100,
-            default_buffer_size: 1024,
-            driver,
+use audio_interrogator::devices::{self, card_filter_index, resolve_card_number, BackendStatus};
+use audio_interrogator::{
+    bundle, controls, diff, eld, hda_codec, jacks, matrix, midi, mixer, owners, pcm_status, sysfs, ucm,
+    usb_stream, AudioDeviceInfo, StreamCapabilities, SysRoot,
+};
+
+fn print_device_info(device: &AudioDeviceInfo) {
+    println!("┌─ Device: {}", device.name);
+    if let Some(ref stable_id) = device.stable_id {
+        if *stable_id != device.name {
+            println!("├─ Stable ID: {}", stable_id);
         }
     }
-
-    fn update_device_type(&mut self) {
-        self.device_type = match (self.input_channels > 0, self.output_channels > 0) {
-            (true, true) => "Input/Output".to_string(),
-            (true, false) => "Input".to_string(),
-            (false, true) => "Output".to_string(),
-            (false, false) => "Unknown".to_string(),
-        };
+    if let Some(ref description) = device.description {
+        println!("├─ Description: {}", description);
     }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct SystemAudioInfo {
-    devices: Vec<AudioDeviceInfo>,
-    default_input: Option<String>,
-    default_output: Option<String>,
-    total_input_devices: usize,
-    total_output_devices: usize,
-}
-
-fn get_cpal_devices() -> Result<Vec<AudioDeviceInfo>> {
-    use cpal::traits::{DeviceTrait, HostTrait};
-
-    let mut devices = Vec::new();
-
-    // Get the default host
-    let host = cpal::default_host();
-
-    // Iterate through all available devices
-    for device in host.devices()? {
-        let device_name = device.name().unwrap_or_else(|_| "Unknown Device".to_string());
+    println!("├─ Type: {}", device.device_type);
+    println!("├─ Driver: {}", device.driver);
+    println!("├─ Input Channels: {}", device.input_channels);
+    println!("├─ Output Channels: {}", device.output_channels);
+    println!("├─ Default Sample Rate: {} Hz", device.default_sample_rate);
+    println!("├─ Default Buffer Size: {} samples", device.default_buffer_size);
 
-        // Get supported input configs
-        let input_channels = match device.default_input_config() {
-            Ok(config) => config.channels() as u32,
-            Err(_) => 0,
-        };
+    if !device.supported_sample_rates.is_empty() {
+        println!("├─ Supported Sample Rates: {:?} Hz", device.supported_sample_rates);
+    }
 
-        // Get supported output configs
-        let output_channels = match device.default_output_config() {
-            Ok(config) => config.channels() as u32,
-            Err(_) => 0,
-        };
+    if let Some(ref playback) = device.playback {
+        print_stream_capabilities("Playback", playback);
+    }
+    if let Some(ref capture) = device.capture {
+        print_stream_capabilities("Capture", capture);
+    }
 
-        // Get supported sample rates
-        let mut supported_sample_rates = Vec::new();
-        let mut default_sample_rate = 44100;
-        let mut default_buffer_size = 1024;
-
-        // Try to get input config ranges
-        if let Ok(input_configs) = device.supported_input_configs() {
-            for config in input_configs {
-                supported_sample_rates.push(config.min_sample_rate().0);
-                supported_sample_rates.push(config.max_sample_rate().0);
-                if let Ok(default_config) = device.default_input_config() {
-                    default_sample_rate = default_config.sample_rate().0;
-                    default_buffer_size = 1024; // CPAL doesn't directly expose buffer size
-                }
-            }
-        }
+    if let Some(ref stream) = device.usb_stream {
+        usb_stream::print_usb_stream(stream);
+    }
 
-        // Try to get output config ranges if no input configs
-        if supported_sample_rates.is_empty() {
-            if let Ok(output_configs) = device.supported_output_configs() {
-                for config in output_configs {
-                    supported_sample_rates.push(config.min_sample_rate().0);
-                    supported_sample_rates.push(config.max_sample_rate().0);
-                    if let Ok(default_config) = device.default_output_config() {
-                        default_sample_rate = default_config.sample_rate().0;
-                    }
-                }
-            }
-        }
+    if let Some(ref identity) = device.hardware {
+        sysfs::print_card_identity(identity);
+    }
 
-        // Remove duplicates and sort
-        supported_sample_rates.sort_unstable();
-        supported_sample_rates.dedup();
+    if let Some(ref sink) = device.hdmi_sink {
+        println!("├─ HDMI Sink: {}", sink.summary());
+    }
 
-        // Common buffer sizes (since CPAL doesn't expose this directly)
-        let supported_buffer_sizes = vec![64, 128, 256, 512, 1024, 2048, 4096];
+    if !device.jacks.is_empty() {
+        let states: Vec<String> = device.jacks.iter()
+            .map(|jack| format!("{}: {}", jack.connector, jack.state_label()))
+            .collect();
+        println!("├─ Jacks: {}", states.join(", "));
+    }
 
-        let device_type = match (input_channels > 0, output_channels > 0) {
-            (true, true) => "Input/Output".to_string(),
-            (true, false) => "Input".to_string(),
-            (false, true) => "Output".to_string(),
-            (false, false) => "Unknown".to_string(),
-        };
+    if device.in_use {
+        println!("├─ In Use: yes");
+    }
+    pcm_status::print_subdevices(&device.subdevices);
 
-        devices.push(AudioDeviceInfo {
-            name: device_name,
-            device_type,
-            input_channels,
-            output_channels,
-            supported_sample_rates,
-            supported_buffer_sizes,
-            default_sample_rate,
-            default_buffer_size,
-            driver: "CPAL".to_string(),
-        });
+    if !device.supported_period_sizes.is_empty() {
+        println!("├─ Supported Period Sizes: {:?} samples ({})",
+            device.supported_period_sizes, device.period_size_source.label());
     }
 
-    Ok(devices)
+    println!("└─ Supported Buffer Sizes: {:?} samples ({})",
+        device.supported_buffer_sizes, device.buffer_size_source.label());
+    println!();
 }
 
-#[cfg(target_os = "linux")]
-fn get_alsa_devices(no_proc: bool) -> Result<Vec<AudioDeviceInfo>> {
-    use alsa::{PCM, Direction};
-    use alsa::pcm::{HwParams, Access, Format};
-
-    let mut devices = Vec::new();
-
-    // Get in-use device information without disrupting audio streams (if enabled)
-    if !no_proc {
-        if let Ok(proc_devices) = get_proc_alsa_devices_safe() {
-            devices.extend(proc_devices);
+fn print_stream_capabilities(label: &str, stream: &StreamCapabilities) {
+    fn range<T: std::fmt::Display>(min: Option<T>, max: Option<T>) -> String {
+        match (min, max) {
+            (Some(min), Some(max)) => format!("{} - {}", min, max),
+            _ => "unknown".to_string(),
         }
     }
 
-    // Common ALSA device names to check for additional devices
-    let device_names = vec![
-        "default",
-        "hw:0,0", "hw:0,1", "hw:0,2", "hw:0,3",
-        "hw:1,0", "hw:1,1", "hw:1,2", "hw:1,3",
-        "hw:2,0", "hw:2,1", "hw:2,2", "hw:2,3",
-        "plughw:0,0", "plughw:0,1", "plughw:1,0", "plughw:1,1",
-    ];
-
-    for device_name in device_names {
-        // Try to open for playback (output)
-        let mut output_channels = 0;
-        let mut input_channels = 0;
-        let mut supported_rates = Vec::new();
-
-        if let Ok(pcm) = PCM::new(device_name, Direction::Playback, false) {
-            if let Ok(hwp) = HwParams::any(&pcm) {
-                if hwp.set_access(Access::RWInterleaved).is_ok() &&
-                   hwp.set_format(Format::s16()).is_ok() {
-
-                    // Get channel count range
-                    if let Ok(max_ch) = hwp.get_channels_max() {
-                        output_channels = max_ch;
-                    }
-
-                    // Get sample rate range
-                    if let (Ok(min_rate), Ok(max_rate)) = (hwp.get_rate_min(), hwp.get_rate_max()) {
-                        // Add common sample rates within the supported range
-                        let common_rates = vec![8000, 11025, 22050, 44100, 48000, 88200, 96000, 176400, 192000];
-                        for &rate in &common_rates {
-                            if rate >= min_rate && rate <= max_rate {
-                                supported_rates.push(rate);
-                            }
-                        }
-                        if supported_rates.is_empty() {
-                            supported_rates.push(min_rate);
-                            supported_rates.push(max_rate);
-                        }
-                    }
-                }
-            }
-        }
-
-        // Try to open for capture (input)
-        if let Ok(pcm) = PCM::new(device_name, Direction::Capture, false) {
-            if let Ok(hwp) = HwParams::any(&pcm) {
-                if hwp.set_access(Access::RWInterleaved).is_ok() &&
-                   hwp.set_format(Format::s16()).is_ok() {
+    let with_default = |value: String, default: Option<String>| match default {
+        Some(default) => format!("{} (default {})", value, default),
+        None => value,
+    };
 
-                    // Get channel count range
-                    if let Ok(max_ch) = hwp.get_channels_max() {
-                        input_channels = max_ch;
-                    }
+    let mut lines = vec![
+        format!("Channels: {}", with_default(
+            format!("{} - {}", stream.min_channels, stream.max_channels),
+            stream.default_channels.map(|c| c.to_string()),
+        )),
+        format!("Sample Rates: {}", with_default(
+            format!("{:?} Hz", stream.sample_rates),
+            stream.default_sample_rate.map(|r| format!("{} Hz", r)),
+        )),
+        format!("Formats: {}", with_default(stream.formats.join(", "), stream.default_format.clone())),
+    ];
 
-                    // Get sample rate range if not already populated
-                    if supported_rates.is_empty() {
-                        if let (Ok(min_rate), Ok(max_rate)) = (hwp.get_rate_min(), hwp.get_rate_max()) {
-                            let common_rates = vec![8000, 11025, 22050, 44100, 48000, 88200, 96000, 176400, 192000];
-                            for &rate in &common_rates {
-                                if rate >= min_rate && rate <= max_rate {
-                                    supported_rates.push(rate);
-                                }
-                            }
-                            if supported_rates.is_empty() {
-                                supported_rates.push(min_rate);
-                                supported_rates.push(max_rate);
-                            }
-                        }
-                    }
-                }
-            }
+    if let Some(ref hw) = stream.hw {
+        lines.push(format!("Access: {}", hw.access_types.join(", ")));
+        lines.push(format!("Rate Range: {} - {} Hz", hw.min_rate, hw.max_rate));
+        lines.push(format!("Period Size: {} frames", range(hw.min_period_size, hw.max_period_size)));
+        lines.push(format!("Buffer Size: {} frames", range(hw.min_buffer_size, hw.max_buffer_size)));
+        lines.push(format!("Periods: {}", range(hw.min_periods, hw.max_periods)));
+        lines.push(format!("Buffer: {} bytes", range(hw.min_buffer_bytes, hw.max_buffer_bytes)));
+        for map in &hw.channel_maps.supported {
+            lines.push(format!("Channel Map: {}", map.describe()));
         }
-
-        // Only add device if it has input or output capabilities
-        if input_channels > 0 || output_channels > 0 {
-            let device_type = match (input_channels > 0, output_channels > 0) {
-                (true, true) => "Input/Output".to_string(),
-                (true, false) => "Input".to_string(),
-                (false, true) => "Output".to_string(),
-                (false, false) => continue,
-            };
-
-            // Common buffer sizes for ALSA
-            let supported_buffer_sizes = vec![64, 128, 256, 512, 1024, 2048, 4096, 8192];
-
-            devices.push(AudioDeviceInfo {
-                name: device_name.to_string(),
-                device_type,
-                input_channels,
-                output_channels,
-                supported_sample_rates: supported_rates.clone(),
-                supported_buffer_sizes,
-                default_sample_rate: 44100,
-                default_buffer_size: 1024,
-                driver: "ALSA".to_string(),
-            });
+        if let Some(ref current) = hw.channel_maps.current {
+            lines.push(format!("Current Channel Map: {}", current.join(" ")));
         }
     }
 
-    Ok(devices)
-}
-
-#[cfg(not(target_os = "linux"))]
-fn get_alsa_devices(_no_proc: bool) -> Result<Vec<AudioDeviceInfo>> {
-    Ok(Vec::new()) // ALSA is Linux-specific
-}
-
-fn get_system_audio_info(no_proc: bool) -> Result<SystemAudioInfo> {
-    let mut all_devices = Vec::new();
-
-    // Get CPAL devices (cross-platform)
-    match get_cpal_devices() {
-        Ok(mut cpal_devices) => all_devices.append(&mut cpal_devices),
-        Err(e) => eprintln!("Warning: Failed to get CPAL devices: {}", e),
-    }
-
-    // Get ALSA devices (Linux-specific)
-    #[cfg(target_os = "linux")]
-    match get_alsa_devices(no_proc) {
-        Ok(mut alsa_devices) => all_devices.append(&mut alsa_devices),
-        Err(e) => eprintln!("Warning: Failed to get ALSA devices: {}", e),
+    println!("├─ {}:", label);
+    for (i, line) in lines.iter().enumerate() {
+        let branch = if i + 1 == lines.len() { "└─" } else { "├─" };
+        println!("│  {} {}", branch, line);
     }
-
-    let input_count = all_devices.iter().filter(|d| d.input_channels > 0).count();
-    let output_count = all_devices.iter().filter(|d| d.output_channels > 0).count();
-
-    // Try to determine default devices
-    let default_input = all_devices.iter()
-        .find(|d| d.input_channels > 0 && (d.name.contains("default") || d.name.contains("hw:0")))
-        .map(|d| d.name.clone());
-
-    let default_output = all_devices.iter()
-        .find(|d| d.output_channels > 0 && (d.name.contains("default") || d.name.contains("hw:0")))
-        .map(|d| d.name.clone());
-
-    Ok(SystemAudioInfo {
-        devices: all_devices,
-        default_input,
-        default_output,
-        total_input_devices: input_count,
-        total_output_devices: output_count,
-    })
 }
 
-fn print_device_info(device: &AudioDeviceInfo) {
-    println!("┌─ Device: {}", device.name);
-    println!("├─ Type: {}", device.device_type);
-    println!("├─ Driver: {}", device.driver);
-    println!("├─ Input Channels: {}", device.input_channels);
-    println!("├─ Output Channels: {}", device.output_channels);
-    println!("├─ Default Sample Rate: {} Hz", device.default_sample_rate);
-    println!("├─ Default Buffer Size: {} samples", device.default_buffer_size);
-
-    if !device.supported_sample_rates.is_empty() {
-        println!("├─ Supported Sample Rates: {:?} Hz", device.supported_sample_rates);
-    }
-
-    println!("└─ Supported Buffer Sizes: {:?} samples", device.supported_buffer_sizes);
-    println!();
-}
 
 fn main() -> Result<()> {
     let matches = Command::new("Audio Interrogator")
@@ -319,6 +128,7 @@ fn main() -> Result<()> {
                 .short('j')
                 .long("json")
                 .action(clap::ArgAction::SetTrue)
+                .global(true)
                 .help("Output results in JSON format"),
         )
         .arg(
@@ -326,6 +136,7 @@ fn main() -> Result<()> {
                 .short('v')
                 .long("verbose")
                 .action(clap::ArgAction::SetTrue)
+                .global(true)
                 .help("Enable verbose output"),
         )
         .arg(
@@ -333,6 +144,7 @@ fn main() -> Result<()> {
                 .short('a')
                 .long("all")
                 .action(clap::ArgAction::SetTrue)
+                .global(true)
                 .help("Show all devices including duplicates and virtual devices"),
         )
         .arg(
@@ -340,6 +152,7 @@ fn main() -> Result<()> {
                 .short('c')
                 .long("card")
                 .value_name("CARD_ID")
+                .global(true)
                 .help("Filter by specific card ID (e.g., card0, card1, or just 0, 1)"),
         )
         .arg(
@@ -347,6 +160,7 @@ fn main() -> Result<()> {
                 .short('d')
                 .long("device")
                 .value_name("DEVICE_NAME")
+                .global(true)
                 .help("Filter by device name (partial match, case-insensitive)"),
         )
         .arg(
@@ -360,40 +174,375 @@ fn main() -> Result<()> {
             Arg::new("no-proc")
                 .long("no-proc")
                 .action(clap::ArgAction::SetTrue)
+                .global(true)
                 .help("Disable /proc/asound access to prevent interfering with active audio streams"),
         )
+        .arg(
+            Arg::new("sysroot")
+                .long("sysroot")
+                .value_name("DIR")
+                .global(true)
+                .help("Read /proc and /sys below DIR instead of the running system (e.g. a recorded tree)"),
+        )
+        .arg(
+            Arg::new("from-bundle")
+                .long("from-bundle")
+                .value_name("FILE")
+                .global(true)
+                .conflicts_with("sysroot")
+                .help("Replay the interrogation offline from a bundle written by the bundle command"),
+        )
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .value_name("NAMES")
+                .value_delimiter(',')
+                .action(clap::ArgAction::Append)
+                .help("Only run these device backends, comma-separated (cpal, alsa, proc)"),
+        )
+        .subcommand(
+            Command::new("capabilities")
+                .about("Inspect device capabilities in more depth")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("matrix")
+                        .about("Test every format × rate × channels combination against the hardware"),
+                ),
+        )
+        .subcommand(
+            Command::new("controls")
+                .about("List every control element with its range, current values and dB scale"),
+        )
+        .subcommand(
+            Command::new("mixer")
+                .about("Save control values to a JSON profile and restore them later")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("save")
+                        .about("Write the writable control values of the selected cards to a profile")
+                        .arg(Arg::new("file").required(true).help("Profile to write")),
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Show how a profile differs from the present cards, then apply it")
+                        .arg(Arg::new("file").required(true).help("Profile to read"))
+                        .arg(
+                            Arg::new("dry-run")
+                                .long("dry-run")
+                                .action(clap::ArgAction::SetTrue)
+                                .help("Only show the differences, write nothing"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("jacks")
+                .about("List every connector with jack detection and whether it is plugged in"),
+        )
+        .subcommand(
+            Command::new("midi")
+                .about("List RawMIDI devices and sequencer clients and ports, grouped by card"),
+        )
+        .subcommand(
+            Command::new("ucm")
+                .about("Show the UCM verbs, devices and modifiers configured for each card")
+                .arg(
+                    Arg::new("ucm-dir")
+                        .long("ucm-dir")
+                        .value_name("DIR")
+                        .default_value(ucm::DEFAULT_UCM_DIR)
+                        .help("ucm2 configuration directory"),
+                )
+                .arg(
+                    Arg::new("conf")
+                        .long("conf")
+                        .value_name("FILE")
+                        .help("Evaluate this master file (relative to the ucm2 directory) instead of looking up each card's"),
+                ),
+        )
+        .subcommand(
+            Command::new("owners")
+                .about("Show which processes hold PCM, control and MIDI device nodes open"),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare two --json snapshots: devices added, removed and changed")
+                .arg(Arg::new("old").required(true).help("Earlier snapshot"))
+                .arg(Arg::new("new").required(true).help("Later snapshot"))
+                .arg(
+                    Arg::new("threshold")
+                        .long("threshold")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("0")
+                        .help("Exit with status 1 when more than N differences are found"),
+                ),
+        )
+        .subcommand(
+            Command::new("bundle")
+                .about("Copy /proc/asound, sound sysfs, module parameters, ALSA config and this report into one archive")
+                .arg(
+                    Arg::new("file")
+                        .default_value("audio-bundle.tar")
+                        .help("Archive to write"),
+                ),
+        )
         .get_matches();
 
     let json_output = matches.get_flag("json");
     let verbose = matches.get_flag("verbose");
     let show_all = matches.get_flag("all");
-    let card_filter = matches.get_one::<String>("card");
-    let device_filter = matches.get_one::<String>("device");
+    let card_filter = matches.get_one::<String>("card").map(String::as_str);
+    let device_filter = matches.get_one::<String>("device").map(String::as_str);
     let list_cards = matches.get_flag("list-cards");
     let no_proc = matches.get_flag("no-proc");
+    let backends: Option<Vec<&str>> = matches.get_many::<String>("backend")
+        .map(|names| names.map(String::as_str).collect());
+    // Comparing snapshots reads neither the system nor a sysroot
+    if let Some(("diff", sub_matches)) = matches.subcommand() {
+        let old = diff::load_snapshot(Path::new(sub_matches.get_one::<String>("old").expect("required")))?;
+        let new = diff::load_snapshot(Path::new(sub_matches.get_one::<String>("new").expect("required")))?;
+        let threshold = *sub_matches.get_one::<usize>("threshold").expect("has default");
+        let snapshot_diff = diff::diff_snapshots(&old, &new);
+
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&snapshot_diff)?);
+        } else {
+            diff::print_diff(&snapshot_diff);
+        }
+        if snapshot_diff.differences > threshold {
+            if !json_output {
+                println!("More than {} difference(s), exiting with status 1", threshold);
+            }
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let bundle = matches.get_one::<String>("from-bundle")
+        .map(|file| bundle::Bundle::open(Path::new(file)))
+        .transpose()?;
+    let sysroot = match bundle {
+        Some(ref bundle) => bundle.sysroot(),
+        None => matches.get_one::<String>("sysroot").map_or_else(SysRoot::host, SysRoot::new),
+    };
+
+    // Subcommands that open ALSA devices have nothing to read in a recorded tree
+    let live_only = |command: &str| -> Result<()> {
+        if !sysroot.is_host() {
+            anyhow::bail!("{} reads the running system and cannot be used with --sysroot or --from-bundle", command);
+        }
+        Ok(())
+    };
 
     // Handle list-cards mode
     if list_cards {
-        list_available_cards()?;
+        list_available_cards(&sysroot)?;
+        return Ok(());
+    }
+
+    if let Some(("capabilities", sub_matches)) = matches.subcommand() {
+        if let Some(("matrix", _)) = sub_matches.subcommand() {
+            live_only("capabilities matrix")?;
+            let options = devices::ProbeOptions { no_proc: true, ..Default::default() };
+            let mut devices = devices::get_alsa_devices(&options)?;
+            devices::attach_card_identities(&mut devices, &sysroot);
+            let devices = devices::filter_devices(devices, &sysroot, card_filter, device_filter, show_all);
+            let matrices = matrix::build_capability_matrices(&devices);
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&matrices)?);
+            } else {
+                matrix::print_capability_matrices(&matrices);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(("controls", _)) = matches.subcommand() {
+        live_only("controls")?;
+        let card = card_filter_index(&sysroot, card_filter)?;
+        let cards = controls::read_all_controls(card);
+
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&cards)?);
+        } else {
+            controls::print_controls(&cards);
+        }
+        return Ok(());
+    }
+
+    if let Some(("mixer", sub_matches)) = matches.subcommand() {
+        live_only("mixer")?;
+        let card = card_filter_index(&sysroot, card_filter)?;
+        match sub_matches.subcommand() {
+            Some(("save", save_matches)) => {
+                let file = save_matches.get_one::<String>("file").expect("required");
+                let profile = mixer::save_profile(&sysroot, card);
+                if profile.cards.is_empty() {
+                    anyhow::bail!("No cards with controls to save");
+                }
+                std::fs::write(file, serde_json::to_string_pretty(&profile)? + "\n")?;
+                let controls: usize = profile.cards.iter().map(|c| c.controls.len()).sum();
+                println!("Saved {} controls of {} card(s) to {}", controls, profile.cards.len(), file);
+            }
+            Some(("restore", restore_matches)) => {
+                let file = restore_matches.get_one::<String>("file").expect("required");
+                let dry_run = restore_matches.get_flag("dry-run");
+                let profile = mixer::load_profile(Path::new(file))?;
+                let plans = mixer::plan_restore(&profile, &mixer::present_cards(&sysroot, card));
+
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&plans)?);
+                } else {
+                    mixer::print_restore_plan(&plans);
+                }
+                if dry_run {
+                    if !json_output {
+                        println!("Dry run, nothing written");
+                    }
+                } else {
+                    let written = mixer::apply_restore(&plans)?;
+                    if !json_output {
+                        println!("Restored {} control(s)", written);
+                    }
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    if let Some(("jacks", _)) = matches.subcommand() {
+        live_only("jacks")?;
+        let card = card_filter_index(&sysroot, card_filter)?;
+        let jacks = jacks::read_all_jacks(card);
+
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&jacks)?);
+        } else {
+            jacks::print_jacks(&jacks);
+        }
+        return Ok(());
+    }
+
+    if let Some(("midi", _)) = matches.subcommand() {
+        live_only("midi")?;
+        let card = card_filter_index(&sysroot, card_filter)?;
+        let report = midi::filter_midi(midi::read_midi(), card, device_filter);
+
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            midi::print_midi(&report);
+        }
+        return Ok(());
+    }
+
+    if let Some(("ucm", sub_matches)) = matches.subcommand() {
+        live_only("ucm")?;
+        let card = card_filter_index(&sysroot, card_filter)?;
+        let ucm_dir = Path::new(sub_matches.get_one::<String>("ucm-dir").expect("has default"));
+        let cards = match sub_matches.get_one::<String>("conf") {
+            // A given file is evaluated for the selected card, or for no card at all
+            Some(conf) => {
+                let vars = match card {
+                    Some(card) => ucm::card_vars(card)?,
+                    None => ucm::CardVars::default(),
+                };
+                vec![ucm::read_ucm_config(ucm_dir, &ucm_dir.join(conf), &vars)]
+            }
+            None => ucm::read_all_ucm(ucm_dir, card),
+        };
+
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&cards)?);
+        } else {
+            ucm::print_ucm(&cards);
+        }
+        return Ok(());
+    }
+
+    if let Some(("owners", _)) = matches.subcommand() {
+        let card = card_filter_index(&sysroot, card_filter)?;
+        let report = owners::find_owners(&sysroot, card);
+
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            owners::print_owners(&report);
+        }
+        return Ok(());
+    }
+
+    if let Some(("bundle", sub_matches)) = matches.subcommand() {
+        if bundle.is_some() {
+            anyhow::bail!("bundle cannot be used with --from-bundle");
+        }
+        if no_proc {
+            anyhow::bail!("bundle copies /proc/asound and cannot be used with --no-proc");
+        }
+        let file = Path::new(sub_matches.get_one::<String>("file").expect("has default"));
+        let mut registry = devices::BackendRegistry::with_builtin();
+        if let Some(ref names) = backends {
+            registry.select(names)?;
+        }
+        let report = devices::interrogate(&registry, &devices::ProbeOptions { no_proc, sysroot: sysroot.clone() });
+        let manifest = bundle::write_bundle(file, &sysroot, &report)?;
+
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&manifest)?);
+        } else {
+            bundle::print_manifest(file, &manifest);
+        }
         return Ok(());
     }
 
     if verbose && !json_output {
         println!("🎵 Audio Interrogator - Scanning system audio devices...\n");
+        if let Some(manifest) = bundle.as_ref().and_then(|b| b.manifest.as_ref()) {
+            println!("Replaying bundle recorded by audio-interrogator {} on {}\n",
+                manifest.tool_version, manifest.kernel.as_deref().unwrap_or("an unknown kernel"));
+        }
     }
 
-    let mut system_info = get_system_audio_info(no_proc)?;
+    // A bundle replays its recorded devices in place of the live backends
+    let mut registry = match bundle {
+        Some(ref bundle) => bundle.registry(),
+        None => devices::BackendRegistry::with_builtin(),
+    };
+    if let Some(ref names) = backends {
+        registry.select(names)?;
+    }
+    let mut system_info = match bundle {
+        Some(ref bundle) => bundle.interrogate(&registry, no_proc),
+        None => devices::interrogate(&registry, &devices::ProbeOptions { no_proc, sysroot: sysroot.clone() }),
+    };
+
+    // A backend that cannot run here only matters when it was asked for
+    for report in &system_info.backends {
+        match report.status {
+            BackendStatus::Failed { ref error } => eprintln!("Warning: {} backend failed: {}", report.backend, error),
+            BackendStatus::Unavailable { ref reason } if backends.is_some() => {
+                eprintln!("Warning: {} backend is unavailable: {}", report.backend, reason)
+            }
+            _ => {}
+        }
+    }
 
     // Apply filters
     if !show_all {
-        system_info.devices = filter_devices(system_info.devices, card_filter, device_filter, false);
+        system_info.devices = devices::filter_devices(system_info.devices, &sysroot, card_filter, device_filter, false);
     } else {
-        system_info.devices = filter_devices(system_info.devices, card_filter, device_filter, true);
+        system_info.devices = devices::filter_devices(system_info.devices, &sysroot, card_filter, device_filter, true);
+    }
+
+    if let Some(card_id) = card_filter {
+        let card = resolve_card_number(&sysroot, card_id);
+        system_info.cards.retain(|c| Some(c.index) == card);
     }
 
     // Recalculate counts after filtering
-    system_info.total_input_devices = system_info.devices.iter().filter(|d| d.input_channels > 0).count();
-    system_info.total_output_devices = system_info.devices.iter().filter(|d| d.output_channels > 0).count();
+    system_info.update_totals();
 
     if json_output {
         println!("{}", serde_json::to_string_pretty(&system_info)?);
@@ -402,7 +551,20 @@ fn main() -> Result<()> {
         println!("════════════════════════════════════════");
         println!("        AVAILABLE AUDIO CARDS");
         println!("════════════════════════════════════════");
-        show_card_summary()?;
+        show_card_summary(&sysroot)?;
+
+        if verbose {
+            for card in &system_info.cards {
+                for codec in &card.hda_codecs {
+                    println!();
+                    hda_codec::print_hda_codec(card.index, codec);
+                }
+                if !card.elds.is_empty() {
+                    println!();
+                    eld::print_elds(card.index, &card.elds);
+                }
+            }
+        }
 
         println!("\n════════════════════════════════════════");
         println!("         SYSTEM AUDIO SUMMARY");
@@ -410,13 +572,25 @@ fn main() -> Result<()> {
         println!("Total Devices Found: {}", system_info.devices.len());
         println!("Input Devices: {}", system_info.total_input_devices);
         println!("Output Devices: {}", system_info.total_output_devices);
+        if verbose {
+            println!("Backends:");
+            for report in &system_info.backends {
+                println!("  {}", report.summary());
+            }
+        }
 
         if let Some(ref default_input) = system_info.default_input {
-            println!("Default Input: {}", default_input);
+            match system_info.default_input_source {
+                Some(source) => println!("Default Input: {} (from {})", default_input, source.label()),
+                None => println!("Default Input: {}", default_input),
+            }
         }
 
         if let Some(ref default_output) = system_info.default_output {
-            println!("Default Output: {}", default_output);
+            match system_info.default_output_source {
+                Some(source) => println!("Default Output: {} (from {})", default_output, source.label()),
+                None => println!("Default Output: {}", default_output),
+            }
         }
 
         println!("\n════════════════════════════════════════");
@@ -428,13 +602,15 @@ fn main() -> Result<()> {
                 println!("Device #{}", i + 1);
                 print_device_info(device);
             } else {
-                println!("{}: {} ({}) - In: {}, Out: {}, SR: {}Hz",
+                println!("{}: {} ({}) - In: {}, Out: {}, SR: {}Hz{}{}",
                     i + 1,
                     device.name,
                     device.driver,
                     device.input_channels,
                     device.output_channels,
-                    device.default_sample_rate
+                    device.default_sample_rate,
+                    if device.in_use { " [IN USE]" } else { "" },
+                    device.hdmi_sink.as_ref().map_or(String::new(), |sink| format!(" - {}", sink.summary()))
                 );
             }
         }
@@ -449,278 +625,12 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn filter_devices(
-    devices: Vec<AudioDeviceInfo>,
-    card_filter: Option<&String>,
-    device_filter: Option<&String>,
-    show_all: bool
-) -> Vec<AudioDeviceInfo> {
-    let mut filtered = devices;
-
-    // Apply card filter
-    if let Some(card_id) = card_filter {
-        let card_num = if card_id.starts_with("card") {
-            card_id.strip_prefix("card").unwrap_or(card_id)
-        } else {
-            card_id
-        };
-
-        // Get card mapping from system
-        let card_mapping = get_card_mapping().unwrap_or_default();
-        let target_card_name = card_mapping.get(card_num).cloned();
-
-        filtered.retain(|device| {
-            // Match by card number in various formats
-            device.name.contains(&format!("hw:{}", card_num)) ||
-            device.name.contains(&format!("card{}", card_num)) ||
-            // Match by card name if we found it
-            (target_card_name.as_ref().is_some_and(|name| device.name.contains(&format!("CARD={}", name)))) ||
-            // Direct match for card name
-            device.name.contains(&format!("CARD={}", card_id))
-        });
-    }
-
-    // Apply device name filter
-    if let Some(name_filter) = device_filter {
-        let name_lower = name_filter.to_lowercase();
-        let card_descriptions = get_card_descriptions().unwrap_or_default();
-
-        filtered.retain(|device| {
-            // First check device name
-            if device.name.to_lowercase().contains(&name_lower) {
-                return true;
-            }
-
-            // Then check if any card description matches and this device belongs to that card
-            for (card_name, description) in &card_descriptions {
-                if description.to_lowercase().contains(&name_lower) &&
-                   device.name.contains(&format!("CARD={}", card_name)) {
-                    return true;
-                }
-            }
-
-            false
-        });
-    }
-
-    // If not showing all, remove common duplicates
-    if !show_all {
-        let mut seen_names = HashSet::new();
-        filtered.retain(|device| {
-            // Skip obvious virtual/duplicate devices unless specifically requested
-            if device.name.starts_with("dmix:") ||
-               device.name.starts_with("dsnoop:") ||
-               device.name.starts_with("surround") ||
-               device.name.starts_with("iec958:") {
-                return false;
-            }
-
-            // For similar devices, prefer the simpler name
-            let simplified_name = if device.name.starts_with("plughw:") {
-                device.name.replace("plughw:", "hw:")
-            } else {
-                device.name.clone()
-            };
-
-            if seen_names.contains(&simplified_name) {
-                false
-            } else {
-                seen_names.insert(simplified_name);
-                true
-            }
-        });
-    }
-
-    filtered
-}
-
-fn get_card_mapping() -> Result<HashMap<String, String>> {
-    use std::fs;
-    let mut mapping = HashMap::new();
-
-    if let Ok(contents) = fs::read_to_string("/proc/asound/cards") {
-        for line in contents.lines() {
-            // Parse lines like " 0 [HDMI           ]: HDA-Intel - HDA ATI HDMI"
-            if let Some(stripped) = line.strip_prefix(' ') {
-                if let Some(bracket_start) = stripped.find('[') {
-                    if let Some(bracket_end) = stripped.find(']') {
-                        if let Some(card_num) = stripped.split_whitespace().next() {
-                            let card_name = stripped[bracket_start+1..bracket_end].trim().to_string();
-                            mapping.insert(card_num.to_string(), card_name);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(mapping)
-}
-
-fn get_card_descriptions() -> Result<HashMap<String, String>> {
-    use std::fs;
-    let mut descriptions = HashMap::new();
-
-    if let Ok(contents) = fs::read_to_string("/proc/asound/cards") {
-        for line in contents.lines() {
-            // Parse lines like " 0 [HDMI           ]: HDA-Intel - HDA ATI HDMI"
-            if let Some(stripped) = line.strip_prefix(' ') {
-                if let Some(bracket_start) = stripped.find('[') {
-                    if let Some(bracket_end) = stripped.find(']') {
-                        if let Some(dash_pos) = stripped.find(" - ") {
-                            let card_name = stripped[bracket_start+1..bracket_end].trim().to_string();
-                            let description = stripped[dash_pos+3..].trim().to_string();
-                            descriptions.insert(card_name, description);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(descriptions)
-}
-
-#[cfg(target_os = "linux")]
-fn get_proc_alsa_devices_safe() -> Result<Vec<AudioDeviceInfo>> {
-    use std::fs;
-
-
-    let mut devices = Vec::new();
-
-    // Check /proc/asound/ for card directories
-    if let Ok(entries) = fs::read_dir("/proc/asound/") {
-        for entry in entries.flatten() {
-            let name = entry.file_name();
-            if let Some(name_str) = name.to_str() {
-                if let Some(card_num) = name_str.strip_prefix("card") {
-                    let card_path = format!("/proc/asound/{}", name_str);
-
-                    // Check for PCM devices
-                    if let Ok(card_entries) = fs::read_dir(&card_path) {
-                        for card_entry in card_entries.flatten() {
-                            let pcm_name = card_entry.file_name();
-                            if let Some(pcm_str) = pcm_name.to_str() {
-                                // Check for playback devices (pcmXp)
-                                if pcm_str.starts_with("pcm") && pcm_str.ends_with("p") {
-                                    if let Some(device_info) = read_pcm_info_safe(&card_path, pcm_str, "PLAYBACK", card_num) {
-                                        devices.push(device_info);
-                                    }
-                                }
-                                // Check for capture devices (pcmXc)
-                                if pcm_str.starts_with("pcm") && pcm_str.ends_with("c") {
-                                    if let Some(device_info) = read_pcm_info_safe(&card_path, pcm_str, "CAPTURE", card_num) {
-                                        devices.push(device_info);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(devices)
-}
-
-#[cfg(target_os = "linux")]
-fn read_pcm_info_safe(card_path: &str, pcm_dir: &str, stream_type: &str, card_num: &str) -> Option<AudioDeviceInfo> {
-    use std::fs;
-
-    let info_path = format!("{}/{}/info", card_path, pcm_dir);
-    let stream_path = format!("{}/stream0", card_path);
-
-    if let Ok(info_content) = fs::read_to_string(&info_path) {
-        // Parse device number from pcmXp or pcmXc
-        let device_num = if pcm_dir.len() > 4 {
-            &pcm_dir[3..pcm_dir.len()-1]
-        } else {
-            "0"
-        };
-
-        // Get card name from card mapping
-        let card_mapping = get_card_mapping().unwrap_or_default();
-        let _card_name = card_mapping.get(card_num).cloned().unwrap_or_else(|| format!("card{}", card_num));
-
-        let device_name = format!("hw:{},{}", card_num, device_num);
-        let mut device = AudioDeviceInfo::new(device_name, "ALSA".to_string());
-
-        // Try to get channel info from stream0 file (read-only, non-invasive)
-        if let Ok(stream_content) = fs::read_to_string(&stream_path) {
-            if let Some(channels) = parse_stream_channels_safe(&stream_content, stream_type) {
-                match stream_type {
-                    "PLAYBACK" => device.output_channels = channels,
-                    "CAPTURE" => device.input_channels = channels,
-                    _ => {}
-                }
-            } else {
-                // If we can't parse channels from stream, use conservative defaults
-                match stream_type {
-                    "PLAYBACK" => device.output_channels = 2,
-                    "CAPTURE" => device.input_channels = 2,
-                    _ => {}
-                }
-            }
-        } else {
-            // Fallback: assume stereo if we can't read stream info
-            match stream_type {
-                "PLAYBACK" => device.output_channels = 2,
-                "CAPTURE" => device.input_channels = 2,
-                _ => {}
-            }
-        }
-
-        device.update_device_type();
-
-        // Check if device is in use (read-only check)
-        let in_use = info_content.contains("subdevices_avail: 0") &&
-                    info_content.contains("subdevices_count: 1");
-
-        if in_use {
-            device.name = format!("{} (IN USE)", device.name);
-        }
-
-        return Some(device);
-    }
-
-    None
-}
-
-#[cfg(target_os = "linux")]
-fn parse_stream_channels_safe(stream_content: &str, stream_type: &str) -> Option<u32> {
-    let section_start = if stream_type == "PLAYBACK" {
-        "Playback:"
-    } else {
-        "Capture:"
-    };
-
-    if let Some(start_pos) = stream_content.find(section_start) {
-        let section = &stream_content[start_pos..];
-        for line in section.lines() {
-            if line.trim().starts_with("Channels:") {
-                if let Some(channels_str) = line.split(':').nth(1) {
-                    if let Ok(channels) = channels_str.trim().parse::<u32>() {
-                        return Some(channels);
-                    }
-                }
-            }
-        }
-    }
-
-    None
-}
-
-#[cfg(not(target_os = "linux"))]
-fn get_proc_alsa_devices_safe() -> Result<Vec<AudioDeviceInfo>> {
-    Ok(Vec::new())
-}
-
-fn show_card_summary() -> Result<()> {
+fn show_card_summary(root: &SysRoot) -> Result<()> {
     use std::fs;
 
     // Check /proc/asound/cards for card information
-    if let Ok(contents) = fs::read_to_string("/proc/asound/cards") {
+    let cards_path = root.asound().join("cards");
+    if let Ok(contents) = fs::read_to_string(&cards_path) {
         for line in contents.lines() {
             if let Some(card_line) = line.strip_prefix(' ') {
                 if !card_line.trim().is_empty() && !card_line.starts_with('-') {
@@ -729,25 +639,25 @@ fn show_card_summary() -> Result<()> {
             }
         }
     } else {
-        println!("  (Could not read /proc/asound/cards)");
+        println!("  (Could not read {})", cards_path.display());
     }
 
     Ok(())
 }
 
-fn list_available_cards() -> Result<()> {
+fn list_available_cards(root: &SysRoot) -> Result<()> {
     use std::fs;
 
     println!("Available Audio Cards:");
     println!("═════════════════════");
 
-    show_card_summary()?;
+    show_card_summary(root)?;
 
     // Also check for card directories
-    println!("\nCard Directories in /proc/asound/:");
+    println!("\nCard Directories in {}/:", root.asound().display());
     println!("═══════════════════════════════════");
 
-    if let Ok(entries) = fs::read_dir("/proc/asound/") {
+    if let Ok(entries) = fs::read_dir(root.asound()) {
         let mut cards = Vec::new();
         for entry in entries.flatten() {
             let name = entry.file_name();
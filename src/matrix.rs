@@ -0,0 +1,194 @@
+//! Per-combination capability matrix
+//!
+//! A device's flat rate/format/channel lists do not say which combinations
+//! can be opened together. This module tests every format × rate × channels
+//! combination against the ALSA hw_params space of a PCM and keeps the ones
+//! the driver accepts.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{AudioDeviceInfo, HwCapabilities};
+
+/// Channel counts above this are not tested individually (plugins such as
+/// `null` accept absurd ranges)
+const MAX_MATRIX_CHANNELS: u32 = 64;
+
+/// One format/rate/channels combination the device accepts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatrixConfig {
+    pub format: String,
+    pub rate: u32,
+    pub channels: u32,
+}
+
+/// All valid configurations of one device in one direction
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CapabilityMatrix {
+    pub device: String,
+    pub direction: String,
+    pub configurations: Vec<MatrixConfig>,
+}
+
+/// Build matrices for every ALSA device in the list, per available direction
+pub fn build_capability_matrices(devices: &[AudioDeviceInfo]) -> Vec<CapabilityMatrix> {
+    let mut matrices = Vec::new();
+
+    for device in devices.iter().filter(|d| d.driver == "ALSA") {
+        let directions = [
+            ("playback", device.playback.as_ref().and_then(|s| s.hw.as_ref())),
+            ("capture", device.capture.as_ref().and_then(|s| s.hw.as_ref())),
+        ];
+
+        for (direction, hw) in directions {
+            let Some(hw) = hw else { continue };
+            match probe_matrix(&device.name, direction, hw) {
+                Ok(configurations) => matrices.push(CapabilityMatrix {
+                    device: device.name.clone(),
+                    direction: direction.to_string(),
+                    configurations,
+                }),
+                Err(e) => eprintln!("Warning: Failed to build matrix for {} ({}): {}", device.name, direction, e),
+            }
+        }
+    }
+
+    matrices
+}
+
+/// Restrict a copy of the hw_params space to each combination and test it
+///
+/// Every test starts from the unrestricted space again, so an accepted
+/// combination never narrows the next one.
+#[cfg(target_os = "linux")]
+fn probe_matrix(device_name: &str, direction: &str, hw: &HwCapabilities) -> Result<Vec<MatrixConfig>> {
+    use alsa::pcm::{Format, HwParams};
+    use alsa::{Direction, ValueOr, PCM};
+
+    let direction = if direction == "capture" { Direction::Capture } else { Direction::Playback };
+    let pcm = PCM::new(device_name, direction, false)?;
+    let base = HwParams::any(&pcm)?;
+
+    let max_channels = hw.max_channels.min(MAX_MATRIX_CHANNELS);
+    let mut configurations = Vec::new();
+
+    for format_name in &hw.formats {
+        let Ok(format) = format_name.parse::<Format>();
+        let with_format = base.clone();
+        if with_format.set_format(format).is_err() {
+            continue;
+        }
+
+        for &rate in &hw.sample_rates {
+            let with_rate = with_format.clone();
+            if with_rate.set_rate(rate, ValueOr::Nearest).is_err() {
+                continue;
+            }
+
+            for channels in hw.min_channels..=max_channels {
+                if with_rate.test_channels(channels).is_ok() {
+                    configurations.push(MatrixConfig {
+                        format: format_name.clone(),
+                        rate,
+                        channels,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(configurations)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe_matrix(_device_name: &str, _direction: &str, _hw: &HwCapabilities) -> Result<Vec<MatrixConfig>> {
+    Ok(Vec::new()) // ALSA is Linux-specific
+}
+
+/// Print matrices as a table, one row per format and rate
+pub fn print_capability_matrices(matrices: &[CapabilityMatrix]) {
+    if matrices.is_empty() {
+        println!("No ALSA devices could be opened for matrix probing.");
+        return;
+    }
+
+    for matrix in matrices {
+        println!("┌─ {} ({})", matrix.device, matrix.direction);
+
+        if matrix.configurations.is_empty() {
+            println!("└─ No valid configurations");
+            println!();
+            continue;
+        }
+
+        println!("│  {:<20} {:>8}  Channels", "Format", "Rate");
+
+        for (format, rate, channels) in matrix_rows(&matrix.configurations) {
+            println!("│  {:<20} {:>8}  {}", format, rate, format_channel_runs(&channels));
+        }
+
+        println!("└─ {} valid configurations", matrix.configurations.len());
+        println!();
+    }
+}
+
+/// One row per format and rate with the channel counts accepted for it
+fn matrix_rows(configurations: &[MatrixConfig]) -> Vec<(&str, u32, Vec<u32>)> {
+    // Configurations are generated format-major, rate-minor, so rows are contiguous
+    let mut rows: Vec<(&str, u32, Vec<u32>)> = Vec::new();
+    for config in configurations {
+        match rows.last_mut() {
+            Some((format, rate, channels)) if *format == config.format && *rate == config.rate => {
+                channels.push(config.channels);
+            }
+            _ => rows.push((&config.format, config.rate, vec![config.channels])),
+        }
+    }
+    rows
+}
+
+/// Collapse sorted channel counts into runs, e.g. [1, 2, 3, 4, 8] -> "1-4, 8"
+fn format_channel_runs(channels: &[u32]) -> String {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &channel in channels {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == channel => *end = channel,
+            _ => runs.push((channel, channel)),
+        }
+    }
+
+    runs.iter()
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_channel_runs() {
+        assert_eq!(format_channel_runs(&[1, 2, 3, 4, 8]), "1-4, 8");
+        assert_eq!(format_channel_runs(&[2]), "2");
+        assert_eq!(format_channel_runs(&[1, 2, 6, 8, 9]), "1-2, 6, 8-9");
+        assert_eq!(format_channel_runs(&[]), "");
+    }
+
+    #[test]
+    fn groups_rows_by_format_and_rate() {
+        let config = |format: &str, rate, channels| MatrixConfig { format: format.to_string(), rate, channels };
+        let configurations = [
+            config("S16_LE", 44100, 1),
+            config("S16_LE", 44100, 2),
+            config("S16_LE", 48000, 2),
+            config("S32_LE", 48000, 2),
+            config("S32_LE", 48000, 4),
+        ];
+        assert_eq!(matrix_rows(&configurations), [
+            ("S16_LE", 44100, vec![1, 2]),
+            ("S16_LE", 48000, vec![2]),
+            ("S32_LE", 48000, vec![2, 4]),
+        ]);
+    }
+}
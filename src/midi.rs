@@ -0,0 +1,479 @@
+//! RawMIDI devices and ALSA sequencer clients
+//!
+//! MIDI ports show up twice: as RawMIDI devices on the card (`hw:1,0`), one
+//! subdevice per physical port, and as ports of the card's kernel sequencer
+//! client, which applications subscribe to. Both are grouped under the card
+//! they belong to; sequencer clients without a card (Midi Through, software
+//! synths, DAWs) are listed separately.
+//!
+//! The alsa crate does not expose a sequencer client's type or card, so the
+//! sequencer is queried through alsa-sys directly.
+
+use serde::{Deserialize, Serialize};
+
+/// Everything MIDI on the system
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MidiReport {
+    pub cards: Vec<MidiCard>,
+    /// Sequencer clients not tied to a card
+    pub clients: Vec<SeqClient>,
+}
+
+/// MIDI devices of one card
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiCard {
+    pub card: u32,
+    pub id: String,
+    pub rawmidi: Vec<RawMidiDevice>,
+    pub seq_clients: Vec<SeqClient>,
+}
+
+/// One RawMIDI device with its subdevices per direction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawMidiDevice {
+    /// ALSA device name, e.g. "hw:1,0"
+    pub name: String,
+    pub device: u32,
+    /// Device id reported by the driver, e.g. "Scarlett 2i4 USB MIDI 1"
+    pub id: String,
+    /// Subdevice names; the length is the subdevice count
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+/// A sequencer client and its ports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeqClient {
+    pub client: i32,
+    pub name: String,
+    /// "kernel" or "user"
+    pub client_type: String,
+    pub card: Option<u32>,
+    /// Process of a user client
+    pub pid: Option<i32>,
+    pub ports: Vec<SeqPort>,
+}
+
+/// A sequencer port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeqPort {
+    pub port: i32,
+    pub name: String,
+    /// Capabilities such as read, write, subs-read, subs-write, duplex
+    pub capabilities: Vec<String>,
+    /// Port types such as midi-generic, hardware, software, application
+    pub port_types: Vec<String>,
+    pub midi_channels: i32,
+    /// Ports this one sends to, as "client:port"
+    pub connected_to: Vec<String>,
+    /// Ports sending to this one
+    pub connected_from: Vec<String>,
+}
+
+/// SND_SEQ_PORT_CAP_* (alsa/seq.h)
+const PORT_CAPS: [(u32, &str); 8] = [
+    (1 << 0, "read"),
+    (1 << 1, "write"),
+    (1 << 2, "sync-read"),
+    (1 << 3, "sync-write"),
+    (1 << 4, "duplex"),
+    (1 << 5, "subs-read"),
+    (1 << 6, "subs-write"),
+    (1 << 7, "no-export"),
+];
+
+/// SND_SEQ_PORT_TYPE_* (alsa/seq.h)
+const PORT_TYPES: [(u32, &str); 15] = [
+    (1 << 0, "specific"),
+    (1 << 1, "midi-generic"),
+    (1 << 2, "midi-gm"),
+    (1 << 3, "midi-gs"),
+    (1 << 4, "midi-xg"),
+    (1 << 5, "midi-mt32"),
+    (1 << 6, "midi-gm2"),
+    (1 << 10, "synth"),
+    (1 << 11, "direct-sample"),
+    (1 << 12, "sample"),
+    (1 << 16, "hardware"),
+    (1 << 17, "software"),
+    (1 << 18, "synthesizer"),
+    (1 << 19, "port"),
+    (1 << 20, "application"),
+];
+
+fn flag_names(bits: u32, table: &[(u32, &str)]) -> Vec<String> {
+    table.iter()
+        .filter(|&&(bit, _)| bits & bit != 0)
+        .map(|&(_, name)| name.to_string())
+        .collect()
+}
+
+/// Enumerate RawMIDI devices and sequencer clients
+#[cfg(target_os = "linux")]
+pub fn read_midi() -> MidiReport {
+    let mut clients = match read_seq_clients() {
+        Ok(clients) => clients,
+        Err(e) => {
+            eprintln!("Warning: ALSA sequencer not available: {}", e);
+            Vec::new()
+        }
+    };
+
+    let mut cards = Vec::new();
+    for card in alsa::card::Iter::new().flatten() {
+        let index = card.get_index() as u32;
+        let id = alsa::Ctl::new(&format!("hw:{}", index), false)
+            .and_then(|ctl| ctl.card_info().and_then(|info| info.get_id().map(str::to_string)))
+            .unwrap_or_default();
+        let rawmidi = match read_rawmidi_devices(index) {
+            Ok(devices) => devices,
+            Err(e) => {
+                eprintln!("Warning: Failed to read MIDI devices of card {}: {}", index, e);
+                Vec::new()
+            }
+        };
+        let (seq_clients, rest) = clients.into_iter().partition(|c: &SeqClient| c.card == Some(index));
+        clients = rest;
+
+        if !rawmidi.is_empty() || !seq_clients.is_empty() {
+            cards.push(MidiCard { card: index, id, rawmidi, seq_clients });
+        }
+    }
+
+    MidiReport { cards, clients }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_midi() -> MidiReport {
+    MidiReport::default() // ALSA is Linux-specific
+}
+
+/// RawMIDI devices of one card
+///
+/// Each direction is queried on its own. The kernel answers ENOENT for a
+/// direction without substreams, and the alsa crate's `rawmidi::Iter` turns
+/// that into an error for the whole device, so input-only and output-only
+/// ports would be lost with it.
+#[cfg(target_os = "linux")]
+fn read_rawmidi_devices(card: u32) -> anyhow::Result<Vec<RawMidiDevice>> {
+    use crate::controls::RawCtl;
+    use alsa_sys::*;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    let raw = RawCtl::open(card)?;
+    let mut devices: Vec<RawMidiDevice> = Vec::new();
+
+    // SAFETY: info is allocated and freed within this block, and the strings
+    // read from it are copied before it is queried again
+    unsafe {
+        let mut info = ptr::null_mut();
+        if snd_rawmidi_info_malloc(&mut info) < 0 {
+            anyhow::bail!("out of memory");
+        }
+        // Query one subdevice of one direction into info
+        let query = |device: i32, stream, subdevice: u32| {
+            snd_rawmidi_info_set_device(info, device as u32);
+            snd_rawmidi_info_set_stream(info, stream);
+            snd_rawmidi_info_set_subdevice(info, subdevice);
+            snd_ctl_rawmidi_info(raw.ctl, info)
+        };
+        let string = |ptr: *const std::ffi::c_char| if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        };
+
+        let mut device = -1;
+        loop {
+            let err = snd_ctl_rawmidi_next_device(raw.ctl, &mut device);
+            if err < 0 {
+                eprintln!("Warning: Failed to list MIDI devices of card {}: {}", card, alsa::Error::new("snd_ctl_rawmidi_next_device", -err));
+                break;
+            }
+            if device < 0 {
+                break;
+            }
+
+            let mut entry = RawMidiDevice {
+                name: format!("hw:{},{}", card, device),
+                device: device as u32,
+                ..Default::default()
+            };
+            for stream in [SND_RAWMIDI_STREAM_INPUT, SND_RAWMIDI_STREAM_OUTPUT] {
+                let err = query(device, stream, 0);
+                if err < 0 {
+                    // No substreams in this direction
+                    if std::io::Error::from_raw_os_error(-err).kind() != std::io::ErrorKind::NotFound {
+                        eprintln!("Warning: Failed to read MIDI device {}: {}", entry.name, alsa::Error::new("snd_ctl_rawmidi_info", -err));
+                    }
+                    continue;
+                }
+                if entry.id.is_empty() {
+                    entry.id = string(snd_rawmidi_info_get_id(info));
+                }
+
+                let mut names = Vec::new();
+                for subdevice in 0..snd_rawmidi_info_get_subdevices_count(info) {
+                    if query(device, stream, subdevice) >= 0 {
+                        names.push(string(snd_rawmidi_info_get_subdevice_name(info)));
+                    }
+                }
+                if stream == SND_RAWMIDI_STREAM_INPUT {
+                    entry.inputs = names;
+                } else {
+                    entry.outputs = names;
+                }
+            }
+            devices.push(entry);
+        }
+
+        snd_rawmidi_info_free(info);
+    }
+
+    Ok(devices)
+}
+
+/// Every sequencer client except our own, with ports and subscriptions
+#[cfg(target_os = "linux")]
+fn read_seq_clients() -> anyhow::Result<Vec<SeqClient>> {
+    use alsa_sys::*;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    let c_str = |ptr: *const std::ffi::c_char| if ptr.is_null() {
+        String::new()
+    } else {
+        // SAFETY: alsa-lib returns NUL-terminated strings owned by the queried object
+        unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+    };
+    let addr_name = |addr: *const snd_seq_addr_t| {
+        // SAFETY: alsa-lib returns a pointer into the queried object
+        let addr = unsafe { &*addr };
+        format!("{}:{}", addr.client, addr.port)
+    };
+
+    let mut clients = Vec::new();
+
+    // SAFETY: every structure is allocated and freed within this block and
+    // only passed to alsa-lib while the sequencer handle is open
+    unsafe {
+        let mut seq = ptr::null_mut();
+        let err = snd_seq_open(&mut seq, c"default".as_ptr(), SND_SEQ_OPEN_DUPLEX, SND_SEQ_NONBLOCK);
+        if err < 0 {
+            anyhow::bail!("{}", alsa::Error::new("snd_seq_open", -err));
+        }
+        let own_client = snd_seq_client_id(seq);
+
+        let mut client_info = ptr::null_mut();
+        let mut port_info = ptr::null_mut();
+        let mut query = ptr::null_mut();
+        if snd_seq_client_info_malloc(&mut client_info) < 0
+            || snd_seq_port_info_malloc(&mut port_info) < 0
+            || snd_seq_query_subscribe_malloc(&mut query) < 0
+        {
+            snd_seq_client_info_free(client_info);
+            snd_seq_port_info_free(port_info);
+            snd_seq_close(seq);
+            anyhow::bail!("out of memory");
+        }
+
+        snd_seq_client_info_set_client(client_info, -1);
+        while snd_seq_query_next_client(seq, client_info) >= 0 {
+            let client = snd_seq_client_info_get_client(client_info);
+            if client == own_client {
+                continue;
+            }
+            let kernel = snd_seq_client_info_get_type(client_info) == SND_SEQ_KERNEL_CLIENT;
+            let card = snd_seq_client_info_get_card(client_info);
+            let pid = snd_seq_client_info_get_pid(client_info);
+
+            let mut ports = Vec::new();
+            snd_seq_port_info_set_client(port_info, client);
+            snd_seq_port_info_set_port(port_info, -1);
+            while snd_seq_query_next_port(seq, port_info) >= 0 {
+                let root = snd_seq_port_info_get_addr(port_info);
+
+                // Read subscribers receive from this port, write subscribers send to it
+                let subscribers = |subs_type| {
+                    let mut addrs = Vec::new();
+                    snd_seq_query_subscribe_set_root(query, root);
+                    snd_seq_query_subscribe_set_type(query, subs_type);
+                    snd_seq_query_subscribe_set_index(query, 0);
+                    while snd_seq_query_port_subscribers(seq, query) >= 0 {
+                        addrs.push(addr_name(snd_seq_query_subscribe_get_addr(query)));
+                        snd_seq_query_subscribe_set_index(query, snd_seq_query_subscribe_get_index(query) + 1);
+                    }
+                    addrs
+                };
+                let connected_to = subscribers(SND_SEQ_QUERY_SUBS_READ);
+                let connected_from = subscribers(SND_SEQ_QUERY_SUBS_WRITE);
+
+                ports.push(SeqPort {
+                    port: snd_seq_port_info_get_port(port_info),
+                    name: c_str(snd_seq_port_info_get_name(port_info)),
+                    capabilities: flag_names(snd_seq_port_info_get_capability(port_info), &PORT_CAPS),
+                    port_types: flag_names(snd_seq_port_info_get_type(port_info), &PORT_TYPES),
+                    midi_channels: snd_seq_port_info_get_midi_channels(port_info),
+                    connected_to,
+                    connected_from,
+                });
+            }
+
+            clients.push(SeqClient {
+                client,
+                name: c_str(snd_seq_client_info_get_name(client_info)),
+                client_type: if kernel { "kernel" } else { "user" }.to_string(),
+                card: u32::try_from(card).ok(),
+                pid: (pid > 0).then_some(pid),
+                ports,
+            });
+        }
+
+        snd_seq_query_subscribe_free(query);
+        snd_seq_port_info_free(port_info);
+        snd_seq_client_info_free(client_info);
+        snd_seq_close(seq);
+    }
+
+    Ok(clients)
+}
+
+/// Restrict the report to one card and/or names containing `device_filter`
+pub fn filter_midi(report: MidiReport, card_filter: Option<u32>, device_filter: Option<&str>) -> MidiReport {
+    let matches = |name: &str| device_filter.is_none_or(|filter| name.to_lowercase().contains(&filter.to_lowercase()));
+    let client_matches = |client: &SeqClient| matches(&client.name) || client.ports.iter().any(|p| matches(&p.name));
+
+    let cards = report.cards.into_iter()
+        .filter(|card| card_filter.is_none_or(|c| c == card.card))
+        .map(|card| MidiCard {
+            rawmidi: card.rawmidi.into_iter()
+                .filter(|d| matches(&d.name) || matches(&d.id) || d.inputs.iter().chain(&d.outputs).any(|s| matches(s)))
+                .collect(),
+            seq_clients: card.seq_clients.into_iter().filter(client_matches).collect(),
+            ..card
+        })
+        .filter(|card| !card.rawmidi.is_empty() || !card.seq_clients.is_empty())
+        .collect();
+
+    // Clients without a card never match a card filter
+    let clients = if card_filter.is_some() {
+        Vec::new()
+    } else {
+        report.clients.into_iter().filter(client_matches).collect()
+    };
+
+    MidiReport { cards, clients }
+}
+
+fn print_seq_client(client: &SeqClient, indent: &str) {
+    let pid = client.pid.map_or(String::new(), |pid| format!(", pid {}", pid));
+    println!("{}Client {}: {} ({}{})", indent, client.client, client.name, client.client_type, pid);
+    for (i, port) in client.ports.iter().enumerate() {
+        let branch = if i + 1 == client.ports.len() { "└─" } else { "├─" };
+        let mut line = format!("{}:{} {} [{}]", client.client, port.port, port.name, port.capabilities.join(","));
+        if !port.port_types.is_empty() {
+            line.push_str(&format!(" {}", port.port_types.join(",")));
+        }
+        if !port.connected_to.is_empty() {
+            line.push_str(&format!(" → {}", port.connected_to.join(", ")));
+        }
+        if !port.connected_from.is_empty() {
+            line.push_str(&format!(" ← {}", port.connected_from.join(", ")));
+        }
+        println!("{}   {} {}", indent, branch, line);
+    }
+}
+
+/// Print RawMIDI devices and sequencer clients per card
+pub fn print_midi(report: &MidiReport) {
+    if report.cards.is_empty() && report.clients.is_empty() {
+        println!("No MIDI devices found.");
+        return;
+    }
+
+    for card in &report.cards {
+        println!("┌─ Card {} ({})", card.card, card.id);
+        for device in &card.rawmidi {
+            println!("├─ RawMIDI {}: {} ({} in, {} out)",
+                device.name, device.id, device.inputs.len(), device.outputs.len());
+            for (direction, names) in [("in", &device.inputs), ("out", &device.outputs)] {
+                for (sub, name) in names.iter().enumerate() {
+                    println!("│  ├─ {},{} {}: {}", device.name, sub, direction, name);
+                }
+            }
+        }
+        for client in &card.seq_clients {
+            print_seq_client(client, "├─ ");
+        }
+        println!();
+    }
+
+    if !report.clients.is_empty() {
+        println!("┌─ Sequencer clients without a card");
+        for client in &report.clients {
+            print_seq_client(client, "├─ ");
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(client: i32, name: &str, card: Option<u32>) -> SeqClient {
+        SeqClient {
+            client,
+            name: name.to_string(),
+            client_type: "kernel".to_string(),
+            card,
+            pid: None,
+            ports: vec![SeqPort {
+                port: 0,
+                name: format!("{} MIDI 1", name),
+                capabilities: flag_names(0x63, &PORT_CAPS),
+                port_types: flag_names((1 << 1) | (1 << 16) | (1 << 19), &PORT_TYPES),
+                midi_channels: 16,
+                connected_to: vec![],
+                connected_from: vec![],
+            }],
+        }
+    }
+
+    #[test]
+    fn decodes_flags_and_filters_by_card_and_name() {
+        let through = client(14, "Midi Through", None);
+        assert_eq!(through.ports[0].capabilities, ["read", "write", "subs-read", "subs-write"]);
+        assert_eq!(through.ports[0].port_types, ["midi-generic", "hardware", "port"]);
+
+        let report = MidiReport {
+            cards: vec![MidiCard {
+                card: 1,
+                id: "USB".to_string(),
+                rawmidi: vec![RawMidiDevice {
+                    name: "hw:1,0".to_string(),
+                    device: 0,
+                    id: "Scarlett 2i4 USB MIDI 1".to_string(),
+                    inputs: vec!["Scarlett 2i4 USB MIDI 1".to_string()],
+                    outputs: vec!["Scarlett 2i4 USB MIDI 1".to_string()],
+                }],
+                seq_clients: vec![client(24, "Scarlett 2i4 USB", Some(1))],
+            }],
+            clients: vec![through],
+        };
+
+        let by_card = filter_midi(report.clone(), Some(1), None);
+        assert_eq!(by_card.cards.len(), 1);
+        assert!(by_card.clients.is_empty());
+        assert!(filter_midi(report.clone(), Some(0), None).cards.is_empty());
+
+        let by_name = filter_midi(report.clone(), None, Some("through"));
+        assert!(by_name.cards.is_empty());
+        assert_eq!(by_name.clients.len(), 1);
+
+        let by_port = filter_midi(report, None, Some("scarlett"));
+        assert_eq!(by_port.cards[0].rawmidi.len(), 1);
+        assert_eq!(by_port.cards[0].seq_clients.len(), 1);
+    }
+}
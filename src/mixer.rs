@@ -0,0 +1,378 @@
+//! Mixer profiles: saving and restoring control values as JSON
+//!
+//! A profile records the writable control values of selected cards together
+//! with each card's hardware identity. Restoring matches saved cards to the
+//! cards present now by USB serial, USB port path or PCI address rather than
+//! by card number, which changes with plug order, and compares every saved
+//! value with the current one before anything is written.
+
+use crate::controls::{self, CardControls, ControlElement, ControlValues};
+use crate::sysfs::{self, CardIdentity};
+use crate::sysroot::SysRoot;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Profile format version; bumped when the layout changes incompatibly
+pub const PROFILE_VERSION: u32 = 1;
+
+/// A saved set of control values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MixerProfile {
+    pub version: u32,
+    pub cards: Vec<CardProfile>,
+}
+
+/// Saved control values of one card
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardProfile {
+    /// Card number at save time, for reference only
+    pub card: u32,
+    /// Short card id at save time, e.g. "USB"
+    pub id: String,
+    pub identity: Option<CardIdentity>,
+    pub controls: Vec<SavedControl>,
+}
+
+/// Values of one control element, addressed the way alsactl does
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedControl {
+    pub interface: String,
+    pub name: String,
+    #[serde(default)]
+    pub index: u32,
+    #[serde(default)]
+    pub device: u32,
+    #[serde(default)]
+    pub subdevice: u32,
+    pub values: ControlValues,
+}
+
+/// What restoring one saved card would change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardRestorePlan {
+    /// Saved card this plan restores
+    pub saved_card: String,
+    /// Card it was matched to, if any is present
+    pub card: Option<u32>,
+    pub changes: Vec<ControlChange>,
+    pub unchanged: usize,
+    /// Saved controls the card no longer has, or can no longer write
+    pub missing: Vec<String>,
+    /// Saved controls whose values do not fit the present element, and why
+    #[serde(default)]
+    pub invalid: Vec<String>,
+}
+
+/// One control whose current value differs from the saved one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlChange {
+    pub control: String,
+    pub current: Option<ControlValues>,
+    pub saved: ControlValues,
+    #[serde(skip)]
+    element: Option<ControlElement>,
+}
+
+/// Build a profile from the current state of the selected cards
+pub fn save_profile(root: &SysRoot, card_filter: Option<u32>) -> MixerProfile {
+    let cards = controls::read_all_controls(card_filter).into_iter()
+        .map(|card| CardProfile {
+            card: card.card,
+            identity: sysfs::read_card_identity(&root.sys(), card.card),
+            controls: card.controls.iter().filter_map(saved_control).collect(),
+            id: card.id,
+        })
+        .collect();
+
+    MixerProfile { version: PROFILE_VERSION, cards }
+}
+
+/// Controls worth saving: writable, with a readable value that is not a meter
+fn saved_control(element: &ControlElement) -> Option<SavedControl> {
+    if !element.access.writable || element.access.volatile {
+        return None;
+    }
+    Some(SavedControl {
+        interface: element.interface.clone(),
+        name: element.name.clone(),
+        index: element.index,
+        device: element.device,
+        subdevice: element.subdevice,
+        values: element.values.clone()?,
+    })
+}
+
+impl SavedControl {
+    fn matches(&self, element: &ControlElement) -> bool {
+        self.interface == element.interface
+            && self.name == element.name
+            && self.index == element.index
+            && self.device == element.device
+            && self.subdevice == element.subdevice
+    }
+}
+
+impl CardProfile {
+    /// Human-readable name of the saved card
+    pub fn label(&self) -> String {
+        match self.identity.as_ref().and_then(|identity| identity.stable_id.as_ref()) {
+            Some(stable_id) => format!("{} ({})", self.id, stable_id),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// Whether a saved identity refers to the same hardware as a present one
+///
+/// USB devices with a serial number match wherever they are plugged in;
+/// without one they must be the same model on the same port. PCI devices
+/// match by slot address.
+pub fn same_hardware(saved: &CardIdentity, present: &CardIdentity) -> bool {
+    if saved.bus != present.bus {
+        return false;
+    }
+    match saved.bus.as_deref() {
+        Some("usb") => saved.id_pair().is_some() && saved.id_pair() == present.id_pair() && match (&saved.serial, &present.serial) {
+            (Some(saved_serial), Some(serial)) => saved_serial == serial,
+            _ => saved.usb_path.is_some() && saved.usb_path == present.usb_path,
+        },
+        Some("pci") => saved.pci_slot.is_some() && saved.pci_slot == present.pci_slot,
+        _ => saved.stable_id.is_some() && saved.stable_id == present.stable_id,
+    }
+}
+
+/// Compare a profile with the cards present now
+///
+/// Cards saved without a hardware identity cannot be matched and are
+/// reported with no card.
+pub fn plan_restore(profile: &MixerProfile, present: &[(CardControls, Option<CardIdentity>)]) -> Vec<CardRestorePlan> {
+    profile.cards.iter()
+        .map(|saved| {
+            let matched = saved.identity.as_ref().and_then(|saved_identity| present.iter()
+                .find(|(_, identity)| identity.as_ref().is_some_and(|identity| same_hardware(saved_identity, identity))));
+
+            let mut plan = CardRestorePlan {
+                saved_card: saved.label(),
+                card: matched.map(|(card, _)| card.card),
+                changes: Vec::new(),
+                unchanged: 0,
+                missing: Vec::new(),
+                invalid: Vec::new(),
+            };
+            let Some((card, _)) = matched else { return plan };
+
+            for control in &saved.controls {
+                let element = card.controls.iter()
+                    .find(|element| control.matches(element) && element.access.writable);
+                match element {
+                    None => plan.missing.push(control_label(control)),
+                    Some(element) if element.values.as_ref() == Some(&control.values) => plan.unchanged += 1,
+                    // A value of the wrong type or length must never reach the hardware
+                    Some(element) => match element.check_values(&control.values) {
+                        Err(reason) => plan.invalid.push(format!("{}: {}", element.display_name(), reason)),
+                        Ok(()) => plan.changes.push(ControlChange {
+                            control: element.display_name(),
+                            current: element.values.clone(),
+                            saved: control.values.clone(),
+                            element: Some(element.clone()),
+                        }),
+                    },
+                }
+            }
+            plan
+        })
+        .collect()
+}
+
+fn control_label(control: &SavedControl) -> String {
+    if control.index > 0 {
+        format!("'{}',{}", control.name, control.index)
+    } else {
+        format!("'{}'", control.name)
+    }
+}
+
+/// Read the profile in `path`, refusing versions newer than this build understands
+pub fn load_profile(path: &Path) -> anyhow::Result<MixerProfile> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let profile: MixerProfile = serde_json::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("{} is not a mixer profile: {}", path.display(), e))?;
+    if profile.version > PROFILE_VERSION {
+        anyhow::bail!("{} uses profile version {}, this build supports up to {}",
+            path.display(), profile.version, PROFILE_VERSION);
+    }
+    Ok(profile)
+}
+
+/// Current controls and identity of the cards a profile can be restored to
+pub fn present_cards(root: &SysRoot, card_filter: Option<u32>) -> Vec<(CardControls, Option<CardIdentity>)> {
+    controls::read_all_controls(card_filter).into_iter()
+        .map(|card| {
+            let identity = sysfs::read_card_identity(&root.sys(), card.card);
+            (card, identity)
+        })
+        .collect()
+}
+
+/// Write the planned changes; returns how many controls were written
+pub fn apply_restore(plans: &[CardRestorePlan]) -> anyhow::Result<usize> {
+    let mut written = 0;
+
+    for plan in plans {
+        let Some(card) = plan.card else { continue };
+        let writes: Vec<(&ControlElement, &ControlValues)> = plan.changes.iter()
+            .filter_map(|change| Some((change.element.as_ref()?, &change.saved)))
+            .collect();
+        let failures = controls::write_card_controls(card, &writes)?;
+        for (control, reason) in &failures {
+            eprintln!("Warning: Failed to restore {} on card {}: {}", control, card, reason);
+        }
+        written += writes.len() - failures.len();
+    }
+
+    Ok(written)
+}
+
+fn format_values(values: &ControlValues) -> String {
+    match values {
+        ControlValues::Boolean(v) => format!("{:?}", v),
+        ControlValues::Integer(v) => format!("{:?}", v),
+        ControlValues::Enumerated(v) => format!("{:?}", v),
+        ControlValues::Bytes(hex) => hex.clone(),
+    }
+}
+
+/// Print the differences between a profile and the present cards
+pub fn print_restore_plan(plans: &[CardRestorePlan]) {
+    if plans.is_empty() {
+        println!("The profile contains no cards.");
+        return;
+    }
+
+    for plan in plans {
+        let Some(card) = plan.card else {
+            println!("┌─ {}: no matching card present, skipped", plan.saved_card);
+            println!();
+            continue;
+        };
+        println!("┌─ {} → card {}", plan.saved_card, card);
+        for change in &plan.changes {
+            let current = change.current.as_ref().map_or("unreadable".to_string(), format_values);
+            println!("├─ {}: {} → {}", change.control, current, format_values(&change.saved));
+        }
+        for control in &plan.missing {
+            println!("├─ {}: not present on this card, skipped", control);
+        }
+        for control in &plan.invalid {
+            println!("├─ {}, skipped", control);
+        }
+        println!("└─ {} to change, {} unchanged, {} missing, {} invalid",
+            plan.changes.len(), plan.unchanged, plan.missing.len(), plan.invalid.len());
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::ControlAccess;
+    use std::path::PathBuf;
+
+    fn fixture_identity(card: u32) -> CardIdentity {
+        let sys = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysfs");
+        sysfs::read_card_identity(&sys, card).expect("card in fixture")
+    }
+
+    fn element(numid: u32, name: &str, values: ControlValues) -> ControlElement {
+        let count = match values {
+            ControlValues::Integer(ref v) => v.len() as u32,
+            _ => 1,
+        };
+        ControlElement {
+            numid,
+            interface: "MIXER".to_string(),
+            name: name.to_string(),
+            element_type: "INTEGER".to_string(),
+            count,
+            values: Some(values),
+            access: ControlAccess { readable: true, writable: true, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_usb_by_serial_across_ports_and_card_numbers() {
+        let saved = fixture_identity(1);
+        let mut moved = saved.clone();
+        moved.card = 3;
+        moved.usb_path = Some("3-1.4".to_string());
+        assert!(same_hardware(&saved, &moved));
+
+        let mut other_unit = saved.clone();
+        other_unit.serial = Some("Y8XXXXX0999999".to_string());
+        assert!(!same_hardware(&saved, &other_unit));
+
+        // Without serials the port decides
+        let mut no_serial = saved.clone();
+        no_serial.serial = None;
+        assert!(!same_hardware(&no_serial, &CardIdentity { serial: None, ..moved }));
+        assert!(!same_hardware(&saved, &fixture_identity(0)));
+        assert!(same_hardware(&fixture_identity(0), &fixture_identity(0)));
+    }
+
+    #[test]
+    fn plans_only_differing_controls() {
+        let usb = fixture_identity(1);
+        let profile = MixerProfile {
+            version: PROFILE_VERSION,
+            cards: vec![CardProfile {
+                card: 1,
+                id: "USB".to_string(),
+                identity: Some(usb.clone()),
+                controls: vec![
+                    saved_control(&element(1, "Mic Capture Volume", ControlValues::Integer(vec![30, 30]))).unwrap(),
+                    saved_control(&element(2, "Headphone Playback Volume", ControlValues::Integer(vec![100]))).unwrap(),
+                    saved_control(&element(3, "Air Switch", ControlValues::Integer(vec![1]))).unwrap(),
+                    saved_control(&element(4, "Line Out Volume", ControlValues::Integer(vec![0; 200]))).unwrap(),
+                    saved_control(&element(5, "Input Select", ControlValues::Integer(vec![1]))).unwrap(),
+                ],
+            }],
+        };
+
+        // The interface came back as card 2
+        let present = CardControls {
+            card: 2,
+            id: "USB".to_string(),
+            controls: vec![
+                element(5, "Mic Capture Volume", ControlValues::Integer(vec![12, 12])),
+                element(6, "Headphone Playback Volume", ControlValues::Integer(vec![100])),
+                element(7, "Line Out Volume", ControlValues::Integer(vec![5, 5])),
+                ControlElement {
+                    element_type: "ENUMERATED".to_string(),
+                    enum_items: vec!["Line".to_string(), "Inst".to_string()],
+                    ..element(8, "Input Select", ControlValues::Enumerated(vec!["Line".to_string()]))
+                },
+            ],
+        };
+        let plans = plan_restore(&profile, &[(present, Some(CardIdentity { card: 2, ..usb }))]);
+
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].card, Some(2));
+        assert_eq!(plans[0].changes.len(), 1);
+        assert_eq!(plans[0].changes[0].control, "'Mic Capture Volume'");
+        assert_eq!(plans[0].changes[0].current, Some(ControlValues::Integer(vec![12, 12])));
+        assert_eq!(plans[0].changes[0].element.as_ref().map(|e| e.numid), Some(5));
+        assert_eq!(plans[0].unchanged, 1);
+        assert_eq!(plans[0].missing, vec!["'Air Switch'".to_string()]);
+        assert_eq!(plans[0].invalid, vec![
+            "'Line Out Volume': 200 values saved, element has 2".to_string(),
+            "'Input Select': saved value does not fit a ENUMERATED element".to_string(),
+        ]);
+
+        // Nothing matches the PCI controller
+        let plans = plan_restore(&profile, &[(CardControls { card: 0, id: "PCH".to_string(), controls: vec![] },
+            Some(fixture_identity(0)))]);
+        assert_eq!(plans[0].card, None);
+    }
+}
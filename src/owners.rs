@@ -0,0 +1,277 @@
+//! Which processes hold ALSA device nodes open
+//!
+//! Two read-only sources are combined: the `owner_pid` the kernel records in
+//! each open PCM subdevice's `status` file, and a scan of `/proc/*/fd` for
+//! links into `/dev/snd`. The fd scan also catches control, MIDI, hwdep,
+//! timer and sequencer nodes, but only sees processes we are allowed to
+//! inspect; `owner_pid` fills in PCM owners belonging to other users.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::pcm_status;
+use crate::sysroot::SysRoot;
+
+/// How an owning process was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnerSource {
+    /// The process has a file descriptor open on the node
+    OpenFd,
+    /// `owner_pid` in the PCM subdevice's `status` file
+    PcmStatus,
+}
+
+impl OwnerSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OwnerSource::OpenFd => "open fd",
+            OwnerSource::PcmStatus => "pcm status",
+        }
+    }
+}
+
+/// A process holding a device node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnerProcess {
+    pub pid: u32,
+    /// Command line with arguments, or the process name if it has none
+    pub command: String,
+    pub uid: Option<u32>,
+    pub user: Option<String>,
+    pub sources: Vec<OwnerSource>,
+}
+
+/// One `/dev/snd` node and the processes holding it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeOwners {
+    pub node: String,
+    /// pcm_playback, pcm_capture, control, midi, hwdep, timer, sequencer or other
+    pub kind: String,
+    pub card: Option<u32>,
+    pub device: Option<u32>,
+    pub processes: Vec<OwnerProcess>,
+}
+
+/// Everything found by one scan
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OwnersReport {
+    pub nodes: Vec<NodeOwners>,
+    /// Processes whose fd table could not be read (usually other users' without root)
+    pub uninspectable_processes: usize,
+}
+
+/// Scan for owners of every node, optionally restricted to one card
+pub fn find_owners(root: &SysRoot, card_filter: Option<u32>) -> OwnersReport {
+    let mut holders: HashMap<String, Vec<(u32, OwnerSource)>> = HashMap::new();
+    let mut uninspectable_processes = 0;
+
+    // Open file descriptors of every process we can see
+    if let Ok(entries) = fs::read_dir(root.proc()) {
+        for entry in entries.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else { continue };
+            let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+                uninspectable_processes += 1;
+                continue;
+            };
+
+            for fd in fds.flatten() {
+                let Ok(target) = fs::read_link(fd.path()) else { continue };
+                let Some(node) = target.to_str().filter(|t| t.starts_with("/dev/snd/")) else { continue };
+                let list = holders.entry(node.to_string()).or_default();
+                if !list.contains(&(pid, OwnerSource::OpenFd)) {
+                    list.push((pid, OwnerSource::OpenFd));
+                }
+            }
+        }
+    }
+
+    // owner_pid of open PCM subdevices, visible regardless of who owns them
+    for (node, pid) in pcm_status_owners(&root.asound()) {
+        holders.entry(node).or_default().push((pid, OwnerSource::PcmStatus));
+    }
+
+    let passwd = read_passwd(root);
+    let mut nodes: Vec<NodeOwners> = holders.into_iter()
+        .map(|(node, pids)| {
+            let (kind, card, device) = classify_node(&node);
+            let mut processes: Vec<OwnerProcess> = Vec::new();
+            for (pid, source) in pids {
+                match processes.iter_mut().find(|p| p.pid == pid) {
+                    Some(process) => process.sources.push(source),
+                    None => processes.push(describe_process(&root.proc(), pid, source, &passwd)),
+                }
+            }
+            processes.sort_by_key(|p| p.pid);
+            NodeOwners { node, kind: kind.to_string(), card, device, processes }
+        })
+        .filter(|n| card_filter.is_none() || n.card == card_filter)
+        .collect();
+
+    nodes.sort_by(|a, b| a.node.cmp(&b.node));
+
+    OwnersReport { nodes, uninspectable_processes }
+}
+
+/// Map each open PCM subdevice's `owner_pid` to its `/dev/snd/pcmCxDy[pc]` node
+fn pcm_status_owners(asound: &Path) -> Vec<(String, u32)> {
+    let mut owners = Vec::new();
+    let Ok(cards) = fs::read_dir(asound) else { return owners };
+
+    for card_entry in cards.flatten() {
+        let card_name = card_entry.file_name();
+        let Some(card) = card_name.to_str().and_then(|n| n.strip_prefix("card")).and_then(|n| n.parse::<u32>().ok()) else { continue };
+        let Ok(pcms) = fs::read_dir(card_entry.path()) else { continue };
+
+        for pcm_entry in pcms.flatten() {
+            let pcm_name = pcm_entry.file_name();
+            let Some(pcm_str) = pcm_name.to_str().and_then(|n| n.strip_prefix("pcm")) else { continue };
+            let (device, direction, suffix) = match pcm_str.split_at(pcm_str.len().saturating_sub(1)) {
+                (device, "p") => (device, "playback", 'p'),
+                (device, "c") => (device, "capture", 'c'),
+                _ => continue,
+            };
+            let Ok(device) = device.parse::<u32>() else { continue };
+
+            for sub in pcm_status::read_direction_subdevices(&pcm_entry.path(), direction) {
+                if let Some(pid) = sub.status.and_then(|s| s.owner_pid).filter(|&pid| pid > 0) {
+                    owners.push((format!("/dev/snd/pcmC{}D{}{}", card, device, suffix), pid));
+                }
+            }
+        }
+    }
+
+    owners
+}
+
+/// Kind, card and device of a node name such as `pcmC0D3p` or `midiC1D0`
+fn classify_node(node: &str) -> (&'static str, Option<u32>, Option<u32>) {
+    let name = node.rsplit('/').next().unwrap_or(node);
+
+    // "C<card>D<device>" or "C<card>" after the kind prefix
+    let card_device = |rest: &str| -> (Option<u32>, Option<u32>) {
+        let rest = rest.trim_start_matches('C');
+        match rest.split_once('D') {
+            Some((card, device)) => (
+                card.parse().ok(),
+                device.trim_end_matches(['p', 'c']).parse().ok(),
+            ),
+            None => (rest.parse().ok(), None),
+        }
+    };
+
+    if let Some(rest) = name.strip_prefix("pcm") {
+        let (card, device) = card_device(rest);
+        let kind = if rest.ends_with('c') { "pcm_capture" } else { "pcm_playback" };
+        (kind, card, device)
+    } else if let Some(rest) = name.strip_prefix("control") {
+        let (card, _) = card_device(rest);
+        ("control", card, None)
+    } else if let Some(rest) = name.strip_prefix("midi") {
+        let (card, device) = card_device(rest);
+        ("midi", card, device)
+    } else if let Some(rest) = name.strip_prefix("hw") {
+        let (card, device) = card_device(rest);
+        ("hwdep", card, device)
+    } else if name == "timer" {
+        ("timer", None, None)
+    } else if name == "seq" {
+        ("sequencer", None, None)
+    } else {
+        ("other", None, None)
+    }
+}
+
+fn describe_process(proc: &Path, pid: u32, source: OwnerSource, passwd: &HashMap<u32, String>) -> OwnerProcess {
+    let proc_path = proc.join(pid.to_string());
+
+    // cmdline is NUL-separated and empty for kernel threads and zombies
+    let command = fs::read(proc_path.join("cmdline")).ok()
+        .map(|raw| raw.split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect::<Vec<_>>()
+            .join(" "))
+        .filter(|c| !c.is_empty())
+        .or_else(|| fs::read_to_string(proc_path.join("comm")).ok().map(|c| format!("[{}]", c.trim())))
+        .unwrap_or_else(|| "(exited)".to_string());
+
+    // Real UID is the first field of the "Uid:" line
+    let uid = fs::read_to_string(proc_path.join("status")).ok()
+        .and_then(|status| status.lines()
+            .find_map(|line| line.strip_prefix("Uid:"))
+            .and_then(|ids| ids.split_whitespace().next())
+            .and_then(|uid| uid.parse().ok()));
+
+    OwnerProcess {
+        pid,
+        command,
+        uid,
+        user: uid.and_then(|uid| passwd.get(&uid).cloned()),
+        sources: vec![source],
+    }
+}
+
+/// UID to user name from /etc/passwd
+fn read_passwd(root: &SysRoot) -> HashMap<u32, String> {
+    fs::read_to_string(root.root().join("etc/passwd")).unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// Print one block per node with its owning processes
+pub fn print_owners(report: &OwnersReport) {
+    if report.nodes.is_empty() {
+        println!("No processes hold ALSA device nodes open.");
+    }
+
+    for node in &report.nodes {
+        let location = match (node.card, node.device) {
+            (Some(card), Some(device)) => format!(", card {} device {}", card, device),
+            (Some(card), None) => format!(", card {}", card),
+            _ => String::new(),
+        };
+        println!("┌─ {} ({}{})", node.node, node.kind, location);
+
+        for (i, process) in node.processes.iter().enumerate() {
+            let branch = if i + 1 == node.processes.len() { "└─" } else { "├─" };
+            let user = match (&process.user, process.uid) {
+                (Some(user), _) => user.clone(),
+                (None, Some(uid)) => format!("uid {}", uid),
+                (None, None) => "unknown user".to_string(),
+            };
+            let sources: Vec<&str> = process.sources.iter().map(|s| s.label()).collect();
+            println!("{} PID {} ({}): {} [{}]", branch, process.pid, user, process.command, sources.join(", "));
+        }
+        println!();
+    }
+
+    if report.uninspectable_processes > 0 {
+        println!("Note: {} processes could not be inspected; run as root to see every owner.",
+            report.uninspectable_processes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_device_nodes() {
+        assert_eq!(classify_node("/dev/snd/pcmC1D0c"), ("pcm_capture", Some(1), Some(0)));
+        assert_eq!(classify_node("/dev/snd/pcmC0D3p"), ("pcm_playback", Some(0), Some(3)));
+        assert_eq!(classify_node("/dev/snd/controlC0"), ("control", Some(0), None));
+        assert_eq!(classify_node("/dev/snd/hwC0D2"), ("hwdep", Some(0), Some(2)));
+        assert_eq!(classify_node("/dev/snd/midiC2D0"), ("midi", Some(2), Some(0)));
+        assert_eq!(classify_node("/dev/snd/seq"), ("sequencer", None, None));
+        assert_eq!(classify_node("/dev/snd/timer"), ("timer", None, None));
+    }
+}
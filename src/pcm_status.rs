@@ -0,0 +1,302 @@
+//! Live stream state of PCM subdevices from `/proc/asound/cardN/pcmXY/subZ`
+//!
+//! While an application has a subdevice open, the kernel exposes the
+//! parameters it negotiated (`hw_params`, `sw_params`) and the running state
+//! (`status`). A closed subdevice reports `closed` in each file. Reading them
+//! does not touch the stream, so this works while audio is playing.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// One playback or capture subdevice and what is running on it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PcmSubdevice {
+    /// "playback" or "capture"
+    pub direction: String,
+    pub subdevice: u32,
+    /// True when an application has the subdevice open
+    pub in_use: bool,
+    pub hw_params: Option<LiveHwParams>,
+    pub sw_params: Option<LiveSwParams>,
+    pub status: Option<LiveStatus>,
+}
+
+/// Negotiated hardware parameters from `hw_params`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LiveHwParams {
+    pub access: Option<String>,
+    pub format: Option<String>,
+    pub subformat: Option<String>,
+    pub channels: Option<u32>,
+    pub rate: Option<u32>,
+    /// Exact rate as a fraction, e.g. "48000/1"
+    pub rate_fraction: Option<String>,
+    pub period_size: Option<u64>,
+    pub buffer_size: Option<u64>,
+}
+
+/// Software parameters from `sw_params`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LiveSwParams {
+    pub tstamp_mode: Option<String>,
+    pub period_step: Option<u32>,
+    pub avail_min: Option<u64>,
+    pub start_threshold: Option<u64>,
+    pub stop_threshold: Option<u64>,
+    pub silence_threshold: Option<u64>,
+    pub silence_size: Option<u64>,
+    pub boundary: Option<u64>,
+}
+
+/// Running state from `status`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LiveStatus {
+    /// RUNNING, PREPARED, XRUN, DRAINING, PAUSED, SUSPENDED, ...
+    pub state: String,
+    pub owner_pid: Option<u32>,
+    pub trigger_time: Option<String>,
+    pub tstamp: Option<String>,
+    /// Frames between the application pointer and what is audible (may be negative)
+    pub delay: Option<i64>,
+    pub avail: Option<i64>,
+    pub avail_max: Option<u64>,
+    pub hw_ptr: Option<u64>,
+    pub appl_ptr: Option<u64>,
+}
+
+/// Read every subdevice of both directions of PCM device `device`
+pub fn read_pcm_subdevices(card_path: &Path, device: u32) -> Vec<PcmSubdevice> {
+    let mut subdevices = read_direction_subdevices(&card_path.join(format!("pcm{}p", device)), "playback");
+    subdevices.extend(read_direction_subdevices(&card_path.join(format!("pcm{}c", device)), "capture"));
+    subdevices
+}
+
+/// Read the `subZ` directories below one `pcmXp` or `pcmXc` directory
+pub fn read_direction_subdevices(pcm_path: &Path, direction: &str) -> Vec<PcmSubdevice> {
+    let Ok(entries) = fs::read_dir(pcm_path) else { return Vec::new() };
+
+    let mut subdevices: Vec<PcmSubdevice> = entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let subdevice = name.to_str()?.strip_prefix("sub")?.parse().ok()?;
+            Some(read_subdevice(&entry.path(), direction, subdevice))
+        })
+        .collect();
+
+    subdevices.sort_by_key(|s| s.subdevice);
+    subdevices
+}
+
+fn read_subdevice(sub_path: &Path, direction: &str, subdevice: u32) -> PcmSubdevice {
+    // Each file is either "closed" or a list of "key: value" lines
+    let read_open = |file: &str| {
+        fs::read_to_string(sub_path.join(file)).ok()
+            .filter(|content| content.trim() != "closed" && !content.trim().is_empty())
+    };
+
+    let hw_params = read_open("hw_params").map(|c| parse_hw_params(&c));
+    let sw_params = read_open("sw_params").map(|c| parse_sw_params(&c));
+    let status = read_open("status").map(|c| parse_status(&c));
+
+    PcmSubdevice {
+        direction: direction.to_string(),
+        subdevice,
+        in_use: hw_params.is_some() || status.is_some(),
+        hw_params,
+        sw_params,
+        status,
+    }
+}
+
+/// Split "key : value" lines; `status` pads keys with spaces before the colon
+fn fields(content: &str) -> impl Iterator<Item = (&str, &str)> {
+    content.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
+pub fn parse_hw_params(content: &str) -> LiveHwParams {
+    let mut params = LiveHwParams::default();
+    for (key, value) in fields(content) {
+        match key {
+            "access" => params.access = Some(value.to_string()),
+            "format" => params.format = Some(value.to_string()),
+            "subformat" => params.subformat = Some(value.to_string()),
+            "channels" => params.channels = value.parse().ok(),
+            "rate" => {
+                // "48000 (48000/1)"
+                params.rate = value.split_whitespace().next().and_then(|r| r.parse().ok());
+                params.rate_fraction = value.split_once('(')
+                    .map(|(_, fraction)| fraction.trim_end_matches(')').to_string());
+            }
+            "period_size" => params.period_size = value.parse().ok(),
+            "buffer_size" => params.buffer_size = value.parse().ok(),
+            _ => {}
+        }
+    }
+    params
+}
+
+pub fn parse_sw_params(content: &str) -> LiveSwParams {
+    let mut params = LiveSwParams::default();
+    for (key, value) in fields(content) {
+        match key {
+            "tstamp_mode" => params.tstamp_mode = Some(value.to_string()),
+            "period_step" => params.period_step = value.parse().ok(),
+            "avail_min" => params.avail_min = value.parse().ok(),
+            "start_threshold" => params.start_threshold = value.parse().ok(),
+            "stop_threshold" => params.stop_threshold = value.parse().ok(),
+            "silence_threshold" => params.silence_threshold = value.parse().ok(),
+            "silence_size" => params.silence_size = value.parse().ok(),
+            "boundary" => params.boundary = value.parse().ok(),
+            _ => {}
+        }
+    }
+    params
+}
+
+pub fn parse_status(content: &str) -> LiveStatus {
+    let mut status = LiveStatus::default();
+    for (key, value) in fields(content) {
+        match key {
+            "state" => status.state = value.to_string(),
+            "owner_pid" => status.owner_pid = value.parse().ok(),
+            "trigger_time" => status.trigger_time = Some(value.to_string()),
+            "tstamp" => status.tstamp = Some(value.to_string()),
+            "delay" => status.delay = value.parse().ok(),
+            "avail" => status.avail = value.parse().ok(),
+            "avail_max" => status.avail_max = value.parse().ok(),
+            "hw_ptr" => status.hw_ptr = value.parse().ok(),
+            "appl_ptr" => status.appl_ptr = value.parse().ok(),
+            _ => {}
+        }
+    }
+    status
+}
+
+/// Print the open subdevices as part of a verbose device listing
+pub fn print_subdevices(subdevices: &[PcmSubdevice]) {
+    let open: Vec<&PcmSubdevice> = subdevices.iter().filter(|s| s.in_use).collect();
+    if open.is_empty() {
+        return;
+    }
+
+    println!("├─ Active Streams:");
+    for sub in open {
+        let state = sub.status.as_ref().map_or("open", |s| s.state.as_str());
+        let owner = sub.status.as_ref()
+            .and_then(|s| s.owner_pid)
+            .map_or(String::new(), |pid| format!(", pid {}", pid));
+        println!("│  ├─ {} sub{}: {}{}", sub.direction, sub.subdevice, state, owner);
+
+        if let Some(ref hw) = sub.hw_params {
+            println!("│  │  ├─ {} {}ch {} Hz, {}",
+                hw.format.as_deref().unwrap_or("?"),
+                hw.channels.map_or("?".to_string(), |c| c.to_string()),
+                hw.rate.map_or("?".to_string(), |r| r.to_string()),
+                hw.access.as_deref().unwrap_or("?"),
+            );
+            println!("│  │  ├─ Period: {} frames, Buffer: {} frames",
+                hw.period_size.map_or("?".to_string(), |p| p.to_string()),
+                hw.buffer_size.map_or("?".to_string(), |b| b.to_string()),
+            );
+        }
+        if let Some(ref sw) = sub.sw_params {
+            println!("│  │  ├─ avail_min {}, start {}, stop {}",
+                sw.avail_min.map_or("?".to_string(), |v| v.to_string()),
+                sw.start_threshold.map_or("?".to_string(), |v| v.to_string()),
+                sw.stop_threshold.map_or("?".to_string(), |v| v.to_string()),
+            );
+        }
+        if let Some(ref status) = sub.status {
+            println!("│  │  └─ hw_ptr {}, appl_ptr {}, delay {}, avail {}",
+                status.hw_ptr.map_or("?".to_string(), |v| v.to_string()),
+                status.appl_ptr.map_or("?".to_string(), |v| v.to_string()),
+                status.delay.map_or("?".to_string(), |v| v.to_string()),
+                status.avail.map_or("?".to_string(), |v| v.to_string()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_running_stream() {
+        let hw_params = parse_hw_params("\
+access: MMAP_INTERLEAVED
+format: S24_3LE
+subformat: STD
+channels: 2
+rate: 44100 (44100/1)
+period_size: 441
+buffer_size: 1764
+");
+        assert_eq!(hw_params.access.as_deref(), Some("MMAP_INTERLEAVED"));
+        assert_eq!(hw_params.format.as_deref(), Some("S24_3LE"));
+        assert_eq!((hw_params.channels, hw_params.rate), (Some(2), Some(44100)));
+        assert_eq!(hw_params.rate_fraction.as_deref(), Some("44100/1"));
+        assert_eq!((hw_params.period_size, hw_params.buffer_size), (Some(441), Some(1764)));
+
+        let status = parse_status("\
+state: RUNNING
+owner_pid   : 2417
+trigger_time: 5321.118226342
+tstamp      : 5407.902746151
+delay       : -64
+avail       : 1828
+avail_max   : 1830
+-----
+hw_ptr      : 4147200
+appl_ptr    : 4147136
+");
+        assert_eq!(status.state, "RUNNING");
+        assert_eq!(status.owner_pid, Some(2417));
+        assert_eq!(status.trigger_time.as_deref(), Some("5321.118226342"));
+        assert_eq!((status.delay, status.avail, status.avail_max), (Some(-64), Some(1828), Some(1830)));
+        assert_eq!((status.hw_ptr, status.appl_ptr), (Some(4147200), Some(4147136)));
+    }
+
+    /// A `pcmXp` directory in the temp dir whose `sub0` holds `files`
+    fn pcm_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("audio-interrogator-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub0")).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join("sub0").join(file), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn reads_running_subdevice() {
+        let dir = pcm_dir("running", &[
+            ("hw_params", "access: RW_INTERLEAVED\nformat: S32_LE\nchannels: 2\nrate: 48000 (48000/1)\n"),
+            ("sw_params", "tstamp_mode: ENABLE\navail_min: 1024\nboundary: 4611686018427387904\n"),
+            ("status", "state: RUNNING\nowner_pid   : 1873\ndelay       : 3072\n"),
+        ]);
+        let subdevices = read_direction_subdevices(&dir, "playback");
+        let _ = fs::remove_dir_all(&dir);
+
+        let [ref playback] = subdevices[..] else { panic!("{:?}", subdevices) };
+        assert!(playback.in_use);
+        assert_eq!(playback.hw_params.as_ref().and_then(|p| p.rate), Some(48000));
+        assert_eq!(playback.sw_params.as_ref().and_then(|p| p.boundary), Some(4611686018427387904));
+        let status = playback.status.as_ref().unwrap();
+        assert_eq!((status.state.as_str(), status.owner_pid), ("RUNNING", Some(1873)));
+    }
+
+    #[test]
+    fn closed_subdevice_is_not_in_use() {
+        let dir = pcm_dir("closed", &[("hw_params", "closed\n"), ("sw_params", "closed\n"), ("status", "closed\n")]);
+        let subdevices = read_direction_subdevices(&dir, "capture");
+        let _ = fs::remove_dir_all(&dir);
+
+        let [ref capture] = subdevices[..] else { panic!("{:?}", subdevices) };
+        assert_eq!((capture.direction.as_str(), capture.in_use), ("capture", false));
+        assert!(capture.hw_params.is_none() && capture.sw_params.is_none() && capture.status.is_none());
+    }
+}
@@ -0,0 +1,264 @@
+//! Hardware identity of sound cards from sysfs
+//!
+//! `/sys/class/sound/cardN/device` links to the bus device behind a card: a
+//! USB interface for USB audio, a PCI function for HDA controllers, a
+//! platform device for SoC audio. From there we read vendor/product IDs,
+//! descriptive strings, the serial number, where the device sits on its bus
+//! and which kernel driver and module are bound to it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Identity of the hardware behind one ALSA card
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardIdentity {
+    pub card: u32,
+    /// Bus subsystem: usb, pci, platform, ...
+    pub bus: Option<String>,
+    /// Vendor ID as four hex digits, e.g. "1235"
+    pub vendor_id: Option<String>,
+    pub product_id: Option<String>,
+    /// PCI subsystem vendor/device, identifying the board rather than the chip
+    pub subsystem_vendor_id: Option<String>,
+    pub subsystem_product_id: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial: Option<String>,
+    /// USB bus-port path such as "1-2.3", stable while the cable stays in the same port
+    pub usb_path: Option<String>,
+    pub usb_bus: Option<u32>,
+    pub usb_device: Option<u32>,
+    /// PCI slot such as "0000:00:1f.3"
+    pub pci_slot: Option<String>,
+    /// Kernel driver bound to the card's device, e.g. "snd-usb-audio"
+    pub kernel_driver: Option<String>,
+    /// Module providing that driver, e.g. "snd_usb_audio"
+    pub kernel_module: Option<String>,
+    /// Resolved sysfs path of the device, relative to the sysfs root
+    pub sysfs_path: Option<String>,
+    /// Card identifier that survives reboots and renumbering, e.g.
+    /// "usb-1235_8210-Y8XXXXX0123456" or "pci-0000:00:1f.3"
+    #[serde(default)]
+    pub stable_id: Option<String>,
+}
+
+/// Read the identity of card `card` below the sysfs root (normally `/sys`)
+pub fn read_card_identity(sys_root: &Path, card: u32) -> Option<CardIdentity> {
+    let device = fs::canonicalize(sys_root.join(format!("class/sound/card{}/device", card))).ok()?;
+    let sys_root = fs::canonicalize(sys_root).unwrap_or_else(|_| sys_root.to_path_buf());
+
+    let mut identity = CardIdentity {
+        card,
+        bus: link_name(&device.join("subsystem")),
+        kernel_driver: link_name(&device.join("driver")),
+        kernel_module: link_name(&device.join("driver/module")),
+        sysfs_path: device.strip_prefix(&sys_root).ok().map(|p| format!("/{}", p.display())),
+        ..Default::default()
+    };
+
+    match identity.bus.as_deref() {
+        Some("usb") => read_usb_identity(&mut identity, &device),
+        Some("pci") => read_pci_identity(&mut identity, &device),
+        _ => {}
+    }
+    identity.stable_id = stable_card_id(&identity, &device);
+
+    Some(identity)
+}
+
+/// Derive the persistent card ID from what does not change across boots
+///
+/// USB devices prefer their serial number, so the ID follows the device to
+/// any port; without a serial the port path is used. PCI cards use their
+/// slot address and anything else its sysfs device name.
+fn stable_card_id(identity: &CardIdentity, device: &Path) -> Option<String> {
+    let id = match (identity.bus.as_deref(), identity.id_pair()) {
+        (Some("usb"), Some(ids)) => {
+            let ids = ids.replace(':', "_");
+            match (&identity.serial, &identity.usb_path) {
+                (Some(serial), _) => format!("usb-{}-{}", ids, serial),
+                (None, Some(path)) => format!("usb-{}-port{}", ids, path),
+                (None, None) => return None,
+            }
+        }
+        (Some("pci"), _) => format!("pci-{}", identity.pci_slot.as_ref()?),
+        (bus, _) => format!("{}-{}", bus.unwrap_or("sys"), device.file_name()?.to_string_lossy()),
+    };
+
+    // Serials and device names may contain spaces or other shell-unfriendly characters
+    Some(id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "._:-".contains(c) { c } else { '_' })
+        .collect())
+}
+
+/// Fill in USB attributes from the device that owns the audio interface
+fn read_usb_identity(identity: &mut CardIdentity, interface: &Path) {
+    // The card links to an interface ("1-2:1.0"); idVendor and friends live on
+    // its parent, the USB device itself ("1-2")
+    let Some(usb_device) = interface.ancestors().find(|dir| dir.join("idVendor").exists()) else { return };
+
+    identity.vendor_id = read_attr(usb_device, "idVendor");
+    identity.product_id = read_attr(usb_device, "idProduct");
+    identity.manufacturer = read_attr(usb_device, "manufacturer");
+    identity.product = read_attr(usb_device, "product");
+    identity.serial = read_attr(usb_device, "serial");
+    identity.usb_bus = read_attr(usb_device, "busnum").and_then(|v| v.parse().ok());
+    identity.usb_device = read_attr(usb_device, "devnum").and_then(|v| v.parse().ok());
+    identity.usb_path = usb_device.file_name().map(|n| n.to_string_lossy().into_owned());
+}
+
+fn read_pci_identity(identity: &mut CardIdentity, function: &Path) {
+    let hex_id = |attr: &str| read_attr(function, attr).map(|v| v.trim_start_matches("0x").to_string());
+
+    identity.vendor_id = hex_id("vendor");
+    identity.product_id = hex_id("device");
+    identity.subsystem_vendor_id = hex_id("subsystem_vendor");
+    identity.subsystem_product_id = hex_id("subsystem_device");
+    identity.pci_slot = function.file_name().map(|n| n.to_string_lossy().into_owned());
+}
+
+/// Trimmed contents of a sysfs attribute, if present and non-empty
+fn read_attr(dir: &Path, attr: &str) -> Option<String> {
+    fs::read_to_string(dir.join(attr)).ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Final component of a symlink's target, e.g. driver -> .../drivers/snd_hda_intel
+fn link_name(link: &Path) -> Option<String> {
+    let target: PathBuf = fs::read_link(link).ok()?;
+    target.file_name().map(|n| n.to_string_lossy().into_owned())
+}
+
+impl CardIdentity {
+    /// Stable ID of PCM device `pcm` on this card
+    pub fn pcm_stable_id(&self, pcm: Option<u32>) -> Option<String> {
+        let card = self.stable_id.as_ref()?;
+        Some(match pcm {
+            Some(pcm) => format!("{}-pcm{}", card, pcm),
+            None => card.clone(),
+        })
+    }
+
+    /// "vendor:product" when both IDs are known
+    pub fn id_pair(&self) -> Option<String> {
+        Some(format!("{}:{}", self.vendor_id.as_ref()?, self.product_id.as_ref()?))
+    }
+
+    /// Where the device sits on its bus, e.g. "usb 1-2" or "pci 0000:00:1f.3"
+    pub fn location(&self) -> Option<String> {
+        let bus = self.bus.as_deref().unwrap_or("unknown");
+        self.usb_path.as_ref()
+            .or(self.pci_slot.as_ref())
+            .map(|path| format!("{} {}", bus, path))
+    }
+}
+
+/// Print the identity as part of a verbose device listing
+pub fn print_card_identity(identity: &CardIdentity) {
+    let mut lines = Vec::new();
+
+    if let Some(location) = identity.location() {
+        lines.push(format!("Bus: {}", location));
+    } else if let Some(ref bus) = identity.bus {
+        lines.push(format!("Bus: {}", bus));
+    }
+    if let Some(ids) = identity.id_pair() {
+        match (&identity.subsystem_vendor_id, &identity.subsystem_product_id) {
+            (Some(vendor), Some(product)) => lines.push(format!("IDs: {} (subsystem {}:{})", ids, vendor, product)),
+            _ => lines.push(format!("IDs: {}", ids)),
+        }
+    }
+    match (&identity.manufacturer, &identity.product) {
+        (Some(manufacturer), Some(product)) => lines.push(format!("Product: {} {}", manufacturer, product)),
+        (None, Some(product)) => lines.push(format!("Product: {}", product)),
+        (Some(manufacturer), None) => lines.push(format!("Manufacturer: {}", manufacturer)),
+        (None, None) => {}
+    }
+    if let Some(ref serial) = identity.serial {
+        lines.push(format!("Serial: {}", serial));
+    }
+    if let Some(ref driver) = identity.kernel_driver {
+        match identity.kernel_module {
+            Some(ref module) => lines.push(format!("Kernel Driver: {} (module {})", driver, module)),
+            None => lines.push(format!("Kernel Driver: {} (built-in)", driver)),
+        }
+    }
+
+    if let Some(ref stable_id) = identity.stable_id {
+        lines.push(format!("Stable Card ID: {}", stable_id));
+    }
+
+    println!("├─ Hardware (card {}):", identity.card);
+    for (i, line) in lines.iter().enumerate() {
+        let branch = if i + 1 == lines.len() { "└─" } else { "├─" };
+        println!("│  {} {}", branch, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysfs")
+    }
+
+    #[test]
+    fn reads_pci_hda_controller() {
+        let identity = read_card_identity(&fixture(), 0).expect("card0 in fixture");
+
+        assert_eq!(identity.bus.as_deref(), Some("pci"));
+        assert_eq!(identity.id_pair().as_deref(), Some("8086:a348"));
+        assert_eq!(identity.subsystem_vendor_id.as_deref(), Some("1028"));
+        assert_eq!(identity.subsystem_product_id.as_deref(), Some("0869"));
+        assert_eq!(identity.pci_slot.as_deref(), Some("0000:00:1f.3"));
+        assert_eq!(identity.kernel_driver.as_deref(), Some("snd_hda_intel"));
+        assert_eq!(identity.kernel_module.as_deref(), Some("snd_hda_intel"));
+        assert_eq!(identity.sysfs_path.as_deref(), Some("/devices/pci0000:00/0000:00:1f.3"));
+        assert_eq!(identity.usb_path, None);
+        assert_eq!(identity.serial, None);
+        assert_eq!(identity.stable_id.as_deref(), Some("pci-0000:00:1f.3"));
+        assert_eq!(identity.pcm_stable_id(Some(3)).as_deref(), Some("pci-0000:00:1f.3-pcm3"));
+    }
+
+    #[test]
+    fn reads_usb_device_behind_interface() {
+        let identity = read_card_identity(&fixture(), 1).expect("card1 in fixture");
+
+        assert_eq!(identity.bus.as_deref(), Some("usb"));
+        assert_eq!(identity.id_pair().as_deref(), Some("1235:8210"));
+        assert_eq!(identity.manufacturer.as_deref(), Some("Focusrite"));
+        assert_eq!(identity.product.as_deref(), Some("Scarlett 2i2 USB"));
+        assert_eq!(identity.serial.as_deref(), Some("Y8XXXXX0123456"));
+        assert_eq!(identity.usb_path.as_deref(), Some("1-2"));
+        assert_eq!(identity.usb_bus, Some(1));
+        assert_eq!(identity.usb_device, Some(4));
+        assert_eq!(identity.location().as_deref(), Some("usb 1-2"));
+        assert_eq!(identity.kernel_driver.as_deref(), Some("snd-usb-audio"));
+        assert_eq!(identity.kernel_module.as_deref(), Some("snd_usb_audio"));
+        assert_eq!(identity.pci_slot, None);
+        assert_eq!(identity.stable_id.as_deref(), Some("usb-1235_8210-Y8XXXXX0123456"));
+        assert_eq!(identity.pcm_stable_id(Some(0)).as_deref(), Some("usb-1235_8210-Y8XXXXX0123456-pcm0"));
+    }
+
+    #[test]
+    fn usb_stable_id_falls_back_to_port_path() {
+        let identity = CardIdentity {
+            bus: Some("usb".to_string()),
+            vendor_id: Some("0d8c".to_string()),
+            product_id: Some("0014".to_string()),
+            usb_path: Some("3-1.4".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(stable_card_id(&identity, Path::new("/sys/devices/x/3-1.4/3-1.4:1.0")).as_deref(),
+            Some("usb-0d8c_0014-port3-1.4"));
+    }
+
+    #[test]
+    fn missing_card_has_no_identity() {
+        assert_eq!(read_card_identity(&fixture(), 7), None);
+    }
+}
@@ -0,0 +1,56 @@
+//! Filesystem root for procfs and sysfs reads
+//!
+//! Everything read from `/proc` and `/sys` is resolved through a [`SysRoot`],
+//! so the same code runs against a recorded tree (`--sysroot DIR`) as against
+//! the live system. Only the file reads move: ALSA, CPAL and the sound server
+//! still talk to the running machine, which is why callers check
+//! [`SysRoot::is_host`] before mixing in anything live.
+
+use std::path::{Path, PathBuf};
+
+/// Directory that stands in for `/` when reading procfs and sysfs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysRoot {
+    root: PathBuf,
+}
+
+impl SysRoot {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The running system
+    pub fn host() -> Self {
+        Self::new("/")
+    }
+
+    /// Whether this is the running system rather than a recorded tree
+    pub fn is_host(&self) -> bool {
+        self.root == Path::new("/")
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `/proc` below the root
+    pub fn proc(&self) -> PathBuf {
+        self.root.join("proc")
+    }
+
+    /// `/proc/asound` below the root
+    pub fn asound(&self) -> PathBuf {
+        self.root.join("proc/asound")
+    }
+
+    /// `/sys` below the root
+    pub fn sys(&self) -> PathBuf {
+        self.root.join("sys")
+    }
+}
+
+impl Default for SysRoot {
+    fn default() -> Self {
+        Self::host()
+    }
+}
@@ -0,0 +1,928 @@
+//! ALSA Use Case Manager (UCM) profiles
+//!
+//! On laptops and SoC boards the meaningful PCMs, and the mixer settings
+//! that route them, are defined by UCM configuration rather than by the
+//! driver. This module reads the ucm2 tree the way alsa-lib does: the card's
+//! master file is located under `conf.d/<driver>/`, parsed in the alsa-lib
+//! configuration syntax, and its `Define`, `Include`, `If` and `Macro`
+//! directives are evaluated against the card before the verbs, devices and
+//! modifiers are read from the result.
+//!
+//! Conditions that need something we cannot evaluate (regular expressions,
+//! unknown condition types) count as false and are reported as unresolved.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where distributions install the ucm2 tree
+pub const DEFAULT_UCM_DIR: &str = "/usr/share/alsa/ucm2";
+
+/// Evaluated UCM configuration of one card
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UcmCard {
+    pub card: Option<u32>,
+    pub card_id: String,
+    /// Master file, relative to the ucm2 directory
+    pub config_file: String,
+    pub syntax: Option<u32>,
+    pub comment: Option<String>,
+    /// Commands `alsactl init` runs for the card
+    pub boot_sequence: Vec<String>,
+    pub verbs: Vec<UcmVerb>,
+    /// `Error` directives the configuration reached
+    pub errors: Vec<String>,
+    /// Conditions that could not be evaluated and were taken as false
+    pub unresolved: Vec<String>,
+}
+
+/// A use case (verb) such as "HiFi" or "Voice Call"
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UcmVerb {
+    pub name: String,
+    pub comment: Option<String>,
+    pub file: Option<String>,
+    pub enable_sequence: Vec<String>,
+    pub disable_sequence: Vec<String>,
+    pub values: BTreeMap<String, String>,
+    pub devices: Vec<UcmDevice>,
+    pub modifiers: Vec<UcmDevice>,
+}
+
+/// A device or modifier of a verb
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UcmDevice {
+    pub name: String,
+    pub comment: Option<String>,
+    pub playback_pcm: Option<String>,
+    pub capture_pcm: Option<String>,
+    /// Devices that cannot be enabled at the same time
+    pub conflicting_devices: Vec<String>,
+    /// Devices this one can be combined with; empty means no restriction
+    pub supported_devices: Vec<String>,
+    pub enable_sequence: Vec<String>,
+    pub disable_sequence: Vec<String>,
+    pub values: BTreeMap<String, String>,
+}
+
+/// What UCM conditions and substitutions may ask about a card
+#[derive(Debug, Clone, Default)]
+pub struct CardVars {
+    pub number: Option<u32>,
+    pub id: String,
+    pub driver: String,
+    pub name: String,
+    pub long_name: String,
+    pub components: String,
+    /// Control element names, for `ControlExists` conditions
+    pub controls: Vec<String>,
+}
+
+// ---------------------------------------------------------------------------
+// alsa-lib configuration syntax
+
+/// A parsed configuration node; compounds keep their keys in file order
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Str(String),
+    Compound(Vec<(String, Node)>),
+    Array(Vec<Node>),
+}
+
+/// Key used for `<file>` include directives
+const INCLUDE_DIRECTIVE: &str = "<include>";
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    file: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &str, file: &'a str) -> Self {
+        Parser { chars: text.chars().collect(), pos: 0, file }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        let line = self.chars[..self.pos.min(self.chars.len())].iter().filter(|&&c| c == '\n').count() + 1;
+        anyhow::anyhow!("{}:{}: {}", self.file, line, message)
+    }
+
+    /// Skip whitespace, comments and the optional separators ';' and ','
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_whitespace() || c == ';' || c == ',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn is_word_char(c: char) -> bool {
+        !c.is_whitespace() && !"{}[]=;,'\"#<>".contains(c)
+    }
+
+    fn quoted(&mut self) -> anyhow::Result<String> {
+        let quote = self.peek().unwrap_or('"');
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some(c) => text.push(c),
+                        None => return Err(self.error("unterminated string")),
+                    }
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn word(&mut self, allow_dots: bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| Self::is_word_char(c) && (allow_dots || c != '.')) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// A dotted key such as `SectionDevice."Speaker"` or `True.Define.X`
+    fn key_path(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut path = Vec::new();
+        loop {
+            let component = match self.peek() {
+                Some('"') | Some('\'') => self.quoted()?,
+                _ => self.word(false),
+            };
+            if component.is_empty() {
+                return Err(self.error("expected a key"));
+            }
+            // Assignment modifiers ("!key", "?key") do not matter when reading
+            path.push(component.trim_start_matches(['!', '?']).to_string());
+            if self.peek() == Some('.') {
+                self.pos += 1;
+            } else {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<Node> {
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let body = self.compound_body(Some('}'))?;
+                Ok(Node::Compound(body))
+            }
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank();
+                    match self.peek() {
+                        Some(']') => {
+                            self.pos += 1;
+                            return Ok(Node::Array(items));
+                        }
+                        None => return Err(self.error("unterminated array")),
+                        _ => items.push(self.value()?),
+                    }
+                }
+            }
+            Some('"') | Some('\'') => Ok(Node::Str(self.quoted()?)),
+            _ => {
+                let word = self.word(true);
+                if word.is_empty() {
+                    return Err(self.error("expected a value"));
+                }
+                Ok(Node::Str(word))
+            }
+        }
+    }
+
+    fn compound_body(&mut self, end: Option<char>) -> anyhow::Result<Vec<(String, Node)>> {
+        let mut entries = Vec::new();
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None if end.is_none() => return Ok(entries),
+                None => return Err(self.error("unterminated compound")),
+                Some(c) if Some(c) == end => {
+                    self.pos += 1;
+                    return Ok(entries);
+                }
+                Some('<') => {
+                    self.pos += 1;
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c != '>') {
+                        self.pos += 1;
+                    }
+                    let path: String = self.chars[start..self.pos].iter().collect();
+                    self.pos += 1;
+                    merge(&mut entries, vec![(INCLUDE_DIRECTIVE.to_string(), Node::Array(vec![Node::Str(path)]))]);
+                }
+                Some(_) => {
+                    let path = self.key_path()?;
+                    self.skip_blank();
+                    if self.peek() == Some('=') {
+                        self.pos += 1;
+                        self.skip_blank();
+                    }
+                    let value = self.value()?;
+                    let entry = path.into_iter().rev()
+                        .fold(value, |node, key| Node::Compound(vec![(key, node)]));
+                    let Node::Compound(entry) = entry else { unreachable!() };
+                    merge(&mut entries, entry);
+                }
+            }
+        }
+    }
+}
+
+fn parse_config(text: &str, file: &str) -> anyhow::Result<Vec<(String, Node)>> {
+    Parser::new(text, file).compound_body(None)
+}
+
+/// Merge `src` into `dst` like alsa-lib: compounds with the same key merge,
+/// arrays are appended to and anything else is replaced
+fn merge(dst: &mut Vec<(String, Node)>, src: Vec<(String, Node)>) {
+    for (key, node) in src {
+        match dst.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => match (existing, node) {
+                (Node::Compound(existing), Node::Compound(node)) => merge(existing, node),
+                (Node::Array(existing), Node::Array(node)) => existing.extend(node),
+                (existing, node) => *existing = node,
+            },
+            None => dst.push((key, node)),
+        }
+    }
+}
+
+fn take(entries: &mut Vec<(String, Node)>, key: &str) -> Option<Node> {
+    let position = entries.iter().position(|(k, _)| k == key)?;
+    Some(entries.remove(position).1)
+}
+
+fn get<'n>(entries: &'n [(String, Node)], key: &str) -> Option<&'n Node> {
+    entries.iter().find(|(k, _)| k == key).map(|(_, node)| node)
+}
+
+fn get_str<'n>(entries: &'n [(String, Node)], key: &str) -> Option<&'n str> {
+    match get(entries, key)? {
+        Node::Str(s) => Some(s),
+        _ => None,
+    }
+}
+
+fn children(node: &Node) -> Vec<(String, Node)> {
+    match node {
+        Node::Compound(entries) => entries.clone(),
+        Node::Array(items) => items.iter().enumerate().map(|(i, n)| (i.to_string(), n.clone())).collect(),
+        Node::Str(_) => Vec::new(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Evaluation
+
+struct Evaluator<'a> {
+    ucm_dir: &'a Path,
+    /// Directory of the card's master file, for relative file names
+    conf_dir: PathBuf,
+    card: &'a CardVars,
+    vars: HashMap<String, String>,
+    macros: HashMap<String, Node>,
+    errors: Vec<String>,
+    unresolved: Vec<String>,
+    includes: usize,
+}
+
+impl Evaluator<'_> {
+    /// Expand `${...}` references; unknown sources (sys, find-card, ...) expand to nothing
+    fn subst(&self, text: &str) -> String {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            // "$$" delays evaluation in alsa-lib; by the time we read it, it is "$"
+            if rest.starts_with("$$") {
+                rest = &rest[1..];
+            }
+            let (value, consumed) = self.reference(rest);
+            out.push_str(&value);
+            rest = &rest[consumed..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Resolve one reference at the start of `text`; returns the value and its length
+    fn reference(&self, text: &str) -> (String, usize) {
+        let Some(body) = text.strip_prefix("${") else { return ("$".to_string(), 1) };
+        // References nest ("${sys:$Path}" never does, but "${var:${...}}" may)
+        let mut depth = 1;
+        let mut end = None;
+        for (i, c) in body.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(end) = end else { return (text.to_string(), text.len()) };
+        let name = &body[..end];
+        let card = self.card;
+        let value = match name {
+            "CardNumber" => card.number.map(|n| n.to_string()).unwrap_or_default(),
+            "CardId" => card.id.clone(),
+            "CardDriver" => card.driver.clone(),
+            "CardName" => card.name.clone(),
+            "CardLongName" => card.long_name.clone(),
+            "CardComponents" => card.components.clone(),
+            "OpenName" => card.number.map(|n| format!("hw:{}", n)).unwrap_or_else(|| card.id.clone()),
+            "ConfName" | "ConfTopDir" => self.ucm_dir.display().to_string(),
+            "ConfDir" => self.conf_dir.display().to_string(),
+            _ => match name.split_once(':') {
+                Some(("var", var)) => self.vars.get(var.trim_start_matches('-').trim_start_matches('@')).cloned().unwrap_or_default(),
+                Some(("env", var)) => std::env::var(var).unwrap_or_default(),
+                _ => String::new(),
+            },
+        };
+        (value, end + 3)
+    }
+
+    fn resolve_file(&self, file: &str) -> PathBuf {
+        match file.strip_prefix('/') {
+            Some(absolute) => self.ucm_dir.join(absolute),
+            None => self.conf_dir.join(file),
+        }
+    }
+
+    fn load(&mut self, path: &Path) -> Vec<(String, Node)> {
+        self.includes += 1;
+        if self.includes > 1000 {
+            self.errors.push("too many includes".to_string());
+            return Vec::new();
+        }
+        let result = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+            .and_then(|text| parse_config(&text, &path.display().to_string()));
+        match result {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.errors.push(e.to_string());
+                Vec::new()
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &[(String, Node)]) -> bool {
+        let field = |evaluator: &Self, key: &str| get_str(condition, key).map(|s| evaluator.subst(s));
+        match get_str(condition, "Type").unwrap_or("") {
+            "AlwaysTrue" => true,
+            "String" => {
+                if let Some(empty) = field(self, "Empty") {
+                    empty.is_empty()
+                } else if let (Some(haystack), Some(needle)) = (field(self, "Haystack"), field(self, "Needle")) {
+                    haystack.contains(&needle)
+                } else {
+                    field(self, "String1") == field(self, "String2")
+                }
+            }
+            "ControlExists" => {
+                let control = field(self, "Control").unwrap_or_default();
+                let name = control_name(&control);
+                self.card.controls.contains(&name)
+            }
+            other => {
+                let detail = get_str(condition, "Regex").map(|r| format!(" {}", r)).unwrap_or_default();
+                let note = format!("{}{}", other, detail);
+                if !self.unresolved.contains(&note) {
+                    self.unresolved.push(note);
+                }
+                false
+            }
+        }
+    }
+
+    /// Apply Define, Include, If and Macro directives until none are left
+    fn evaluate(&mut self, entries: &mut Vec<(String, Node)>) {
+        for _ in 0..1000 {
+            let mut changed = false;
+
+            if let Some(defines) = take(entries, "Define") {
+                for (name, value) in children(&defines) {
+                    if let Node::Str(value) = value {
+                        let value = self.subst(&value);
+                        self.vars.insert(name, value);
+                    }
+                }
+                changed = true;
+            }
+            if let Some(macros) = take(entries, "DefineMacro") {
+                self.macros.extend(children(&macros));
+                changed = true;
+            }
+            if let Some(includes) = take(entries, "Include") {
+                for (_, include) in children(&includes) {
+                    if let Some(file) = get_str(&children(&include), "File") {
+                        let path = self.resolve_file(&self.subst(file));
+                        let mut included = self.load(&path);
+                        self.evaluate(&mut included);
+                        merge(entries, included);
+                    }
+                }
+                changed = true;
+            }
+            if let Some(Node::Array(includes)) = take(entries, INCLUDE_DIRECTIVE) {
+                for include in includes {
+                    if let Node::Str(file) = include {
+                        // "<searchdir:...>" only adjusts alsa-lib's search path
+                        if !file.contains(':') {
+                            let path = self.ucm_dir.join(file.trim_start_matches('/'));
+                            let mut included = self.load(&path);
+                            self.evaluate(&mut included);
+                            merge(entries, included);
+                        }
+                    }
+                }
+                changed = true;
+            }
+            if let Some(conditions) = take(entries, "If") {
+                for (_, block) in children(&conditions) {
+                    let block = children(&block);
+                    let result = get(&block, "Condition").is_some_and(|c| self.condition(&children(c)));
+                    // The branch is evaluated before merging, so its defines
+                    // are visible to the conditions that follow
+                    if let Some(branch) = get(&block, if result { "True" } else { "False" }) {
+                        let mut branch = children(branch);
+                        self.evaluate(&mut branch);
+                        merge(entries, branch);
+                    }
+                }
+                changed = true;
+            }
+            if let Some(calls) = take(entries, "Macro") {
+                for (_, call) in children(&calls) {
+                    for (name, args) in children(&call) {
+                        self.expand_macro(entries, &name, &args);
+                    }
+                }
+                changed = true;
+            }
+            if let Some(error) = take(entries, "Error") {
+                if let Node::Str(message) = error {
+                    self.errors.push(self.subst(&message));
+                }
+                changed = true;
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        for (_, node) in entries.iter_mut() {
+            if let Node::Compound(inner) = node {
+                self.evaluate(inner);
+            }
+        }
+    }
+
+    /// Expand a macro with its arguments bound to `${var:__name}`
+    fn expand_macro(&mut self, entries: &mut Vec<(String, Node)>, name: &str, args: &Node) {
+        let Some(body) = self.macros.get(name).cloned() else {
+            self.errors.push(format!("undefined macro {}", name));
+            return;
+        };
+        let args = match args {
+            Node::Str(text) => parse_config(text, name).unwrap_or_default(),
+            other => children(other),
+        };
+        let saved = self.vars.clone();
+        for (arg, value) in args {
+            if let Node::Str(value) = value {
+                let value = self.subst(&value);
+                self.vars.insert(format!("__{}", arg), value);
+            }
+        }
+        let mut expanded = children(&self.subst_tree(&body));
+        self.evaluate(&mut expanded);
+        self.vars = saved;
+        merge(entries, expanded);
+    }
+
+    fn subst_tree(&self, node: &Node) -> Node {
+        match node {
+            Node::Str(s) => Node::Str(self.subst(s)),
+            Node::Array(items) => Node::Array(items.iter().map(|n| self.subst_tree(n)).collect()),
+            Node::Compound(entries) => Node::Compound(entries.iter()
+                .map(|(k, n)| (self.subst(k), self.subst_tree(n)))
+                .collect()),
+        }
+    }
+
+    // -- Reading the evaluated tree
+
+    fn string(&self, entries: &[(String, Node)], key: &str) -> Option<String> {
+        get_str(entries, key).map(|s| self.subst(s))
+    }
+
+    fn strings(&self, entries: &[(String, Node)], key: &str) -> Vec<String> {
+        get(entries, key).map_or(Vec::new(), |node| children(node).into_iter()
+            .filter_map(|(_, n)| match n {
+                Node::Str(s) => Some(self.subst(&s)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// A sequence alternates commands and their arguments: `cset "name='X' on"`
+    fn sequence(&self, entries: &[(String, Node)], key: &str) -> Vec<String> {
+        let items = get(entries, key).map(children).unwrap_or_default();
+        items.chunks(2)
+            .map(|pair| {
+                let command = match &pair[0].1 {
+                    Node::Str(s) => s.clone(),
+                    _ => "?".to_string(),
+                };
+                match pair.get(1).map(|(_, n)| n) {
+                    Some(Node::Str(arg)) => format!("{} \"{}\"", command, self.subst(arg)),
+                    Some(_) => format!("{} {{...}}", command),
+                    None => command,
+                }
+            })
+            .collect()
+    }
+
+    fn values(&self, entries: &[(String, Node)]) -> BTreeMap<String, String> {
+        get(entries, "Value").map(children).unwrap_or_default().into_iter()
+            .filter_map(|(k, n)| match n {
+                Node::Str(s) => Some((k, self.subst(&s))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn device(&self, name: &str, node: &Node) -> UcmDevice {
+        let entries = children(node);
+        let values = self.values(&entries);
+        UcmDevice {
+            name: self.subst(name),
+            comment: self.string(&entries, "Comment"),
+            playback_pcm: values.get("PlaybackPCM").cloned(),
+            capture_pcm: values.get("CapturePCM").cloned(),
+            conflicting_devices: self.strings(&entries, "ConflictingDevice"),
+            supported_devices: self.strings(&entries, "SupportedDevice"),
+            enable_sequence: self.sequence(&entries, "EnableSequence"),
+            disable_sequence: self.sequence(&entries, "DisableSequence"),
+            values,
+        }
+    }
+
+    fn verb(&mut self, name: &str, declaration: &Node) -> UcmVerb {
+        let declaration = children(declaration);
+        let file = self.string(&declaration, "File");
+        let mut entries = match file {
+            Some(ref file) => {
+                let path = self.resolve_file(file);
+                self.load(&path)
+            }
+            // Verbs may also be written inline
+            None => declaration.clone(),
+        };
+        self.evaluate(&mut entries);
+
+        let verb_section = get(&entries, "SectionVerb").map(children).unwrap_or_default();
+        let sections = |key: &str| get(&entries, key).map(children).unwrap_or_default();
+        UcmVerb {
+            name: self.subst(name),
+            comment: self.string(&declaration, "Comment"),
+            file,
+            enable_sequence: self.sequence(&verb_section, "EnableSequence"),
+            disable_sequence: self.sequence(&verb_section, "DisableSequence"),
+            values: self.values(&verb_section),
+            devices: sections("SectionDevice").iter()
+                .map(|(name, node)| self.device(name, node))
+                .filter(|device| !device.name.is_empty())
+                .collect(),
+            modifiers: sections("SectionModifier").iter()
+                .map(|(name, node)| self.device(name, node))
+                .filter(|modifier| !modifier.name.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// The name out of a control identifier such as "name='Master Playback Switch'"
+fn control_name(control: &str) -> String {
+    let Some(start) = control.find("name=") else { return control.to_string() };
+    let rest = &control[start + 5..];
+    match rest.chars().next() {
+        Some(quote @ ('\'' | '"')) => rest[1..].split(quote).next().unwrap_or("").to_string(),
+        _ => rest.split(',').next().unwrap_or("").to_string(),
+    }
+}
+
+/// Master file of a card, tried in alsa-lib's order
+pub fn find_card_config(ucm_dir: &Path, card: &CardVars) -> Option<PathBuf> {
+    let driver = &card.driver;
+    [
+        format!("conf.d/{}/{}.conf", driver, card.long_name),
+        format!("conf.d/{}/{}.conf", driver, driver),
+        format!("{}/{}.conf", driver, card.long_name),
+        format!("{}/{}.conf", driver, driver),
+    ]
+    .iter()
+    .map(|relative| ucm_dir.join(relative))
+    .find(|path| path.is_file())
+}
+
+/// Evaluate the master file `config` for `card` and read its verbs
+pub fn read_ucm_config(ucm_dir: &Path, config: &Path, card: &CardVars) -> UcmCard {
+    let mut evaluator = Evaluator {
+        ucm_dir,
+        // conf.d entries are symlinks; relative names resolve next to their target
+        conf_dir: fs::canonicalize(config).unwrap_or_else(|_| config.to_path_buf())
+            .parent().map(Path::to_path_buf).unwrap_or_else(|| ucm_dir.to_path_buf()),
+        card,
+        vars: HashMap::new(),
+        macros: HashMap::new(),
+        errors: Vec::new(),
+        unresolved: Vec::new(),
+        includes: 0,
+    };
+
+    let mut entries = evaluator.load(config);
+    evaluator.evaluate(&mut entries);
+
+    let verbs = get(&entries, "SectionUseCase").map(children).unwrap_or_default().iter()
+        .map(|(name, node)| evaluator.verb(name, node))
+        .collect();
+
+    let mut boot_sequence = evaluator.sequence(&entries, "FixedBootSequence");
+    boot_sequence.extend(evaluator.sequence(&entries, "BootSequence"));
+
+    UcmCard {
+        card: card.number,
+        card_id: card.id.clone(),
+        config_file: config.strip_prefix(ucm_dir).unwrap_or(config).display().to_string(),
+        syntax: get_str(&entries, "Syntax").and_then(|s| s.parse().ok()),
+        comment: evaluator.string(&entries, "Comment"),
+        boot_sequence,
+        verbs,
+        errors: evaluator.errors,
+        unresolved: evaluator.unresolved,
+    }
+}
+
+/// What UCM needs to know about a live card
+#[cfg(target_os = "linux")]
+pub fn card_vars(card: u32) -> anyhow::Result<CardVars> {
+    let ctl = alsa::Ctl::new(&format!("hw:{}", card), false)?;
+    let info = ctl.card_info()?;
+    let controls = crate::controls::read_card_controls(card)
+        .map(|c| c.controls.into_iter().map(|element| element.name).collect())
+        .unwrap_or_default();
+
+    Ok(CardVars {
+        number: Some(card),
+        id: info.get_id().unwrap_or_default().to_string(),
+        driver: info.get_driver().unwrap_or_default().to_string(),
+        name: info.get_name().unwrap_or_default().to_string(),
+        long_name: info.get_longname().unwrap_or_default().to_string(),
+        components: info.get_components().unwrap_or_default().to_string(),
+        controls,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn card_vars(_card: u32) -> anyhow::Result<CardVars> {
+    anyhow::bail!("UCM requires ALSA") // ALSA is Linux-specific
+}
+
+/// UCM configuration of every card that has one, optionally just one card
+#[cfg(target_os = "linux")]
+pub fn read_all_ucm(ucm_dir: &Path, card_filter: Option<u32>) -> Vec<UcmCard> {
+    let mut cards = Vec::new();
+
+    for card in alsa::card::Iter::new().flatten() {
+        let index = card.get_index() as u32;
+        if card_filter.is_some_and(|c| c != index) {
+            continue;
+        }
+        let vars = match card_vars(index) {
+            Ok(vars) => vars,
+            Err(e) => {
+                eprintln!("Warning: Failed to read card {} for UCM: {}", index, e);
+                continue;
+            }
+        };
+        if let Some(config) = find_card_config(ucm_dir, &vars) {
+            cards.push(read_ucm_config(ucm_dir, &config, &vars));
+        }
+    }
+
+    cards
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_all_ucm(_ucm_dir: &Path, _card_filter: Option<u32>) -> Vec<UcmCard> {
+    Vec::new() // ALSA is Linux-specific
+}
+
+fn print_device(kind: &str, device: &UcmDevice, last: bool) {
+    let (branch, indent) = if last { ("└─", "   ") } else { ("├─", "│  ") };
+    let comment = device.comment.as_ref().map_or(String::new(), |c| format!(": {}", c));
+    println!("│  {} {} {}{}", branch, kind, device.name, comment);
+
+    let mut lines = Vec::new();
+    if let Some(ref pcm) = device.playback_pcm {
+        lines.push(format!("Playback PCM: {}", pcm));
+    }
+    if let Some(ref pcm) = device.capture_pcm {
+        lines.push(format!("Capture PCM: {}", pcm));
+    }
+    if !device.conflicting_devices.is_empty() {
+        lines.push(format!("Conflicts: {}", device.conflicting_devices.join(", ")));
+    }
+    if !device.supported_devices.is_empty() {
+        lines.push(format!("Supports: {}", device.supported_devices.join(", ")));
+    }
+    lines.extend(device.enable_sequence.iter().map(|c| format!("Enable: {}", c)));
+    lines.extend(device.disable_sequence.iter().map(|c| format!("Disable: {}", c)));
+    for (i, line) in lines.iter().enumerate() {
+        let branch = if i + 1 == lines.len() { "└─" } else { "├─" };
+        println!("│  {}{} {}", indent, branch, line);
+    }
+}
+
+/// Print verbs, devices and modifiers per card
+pub fn print_ucm(cards: &[UcmCard]) {
+    if cards.is_empty() {
+        println!("No UCM configuration found for any card.");
+        return;
+    }
+
+    for card in cards {
+        let label = match card.card {
+            Some(number) => format!("Card {} ({})", number, card.card_id),
+            None => "No card".to_string(),
+        };
+        println!("┌─ {}: {}", label, card.config_file);
+        if let Some(ref comment) = card.comment {
+            println!("├─ {}", comment);
+        }
+        for command in &card.boot_sequence {
+            println!("├─ Boot: {}", command);
+        }
+        for verb in &card.verbs {
+            let comment = verb.comment.as_ref().map_or(String::new(), |c| format!(": {}", c));
+            println!("├─ Verb {}{}", verb.name, comment);
+            for command in &verb.enable_sequence {
+                println!("│  ├─ Enable: {}", command);
+            }
+            for command in &verb.disable_sequence {
+                println!("│  ├─ Disable: {}", command);
+            }
+            let count = verb.devices.len() + verb.modifiers.len();
+            for (i, device) in verb.devices.iter().enumerate() {
+                print_device("Device", device, i + 1 == count);
+            }
+            for (i, modifier) in verb.modifiers.iter().enumerate() {
+                print_device("Modifier", modifier, verb.devices.len() + i + 1 == count);
+            }
+        }
+        for error in &card.errors {
+            println!("├─ Error: {}", error);
+        }
+        if !card.unresolved.is_empty() {
+            println!("├─ Unresolved conditions (taken as false): {}", card.unresolved.join("; "));
+        }
+        println!("└─ {} verb(s)", card.verbs.len());
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ucm2")
+    }
+
+    fn laptop() -> CardVars {
+        CardVars {
+            number: Some(0),
+            id: "PCH".to_string(),
+            driver: "HDA-Intel".to_string(),
+            name: "HDA Intel PCH".to_string(),
+            long_name: "HDA Intel PCH at 0xf1330000 irq 147".to_string(),
+            components: "HDA:10ec0257,17aa2258,00100001 cfg-dmics:2".to_string(),
+            controls: vec!["Master Playback Switch".to_string(), "Speaker Playback Switch".to_string()],
+        }
+    }
+
+    #[test]
+    fn parses_alsa_config_syntax() {
+        let entries = parse_config(r#"
+            Syntax 4  # comment
+            Define.A "x"; Define { B 'y' }
+            SectionDevice."Head phones".Value { PlaybackPCM "hw:${CardId},0" }
+            Seq [ cset "name='Speaker Switch' on" disdevall "" ]
+        "#, "test").unwrap();
+
+        assert_eq!(get_str(&entries, "Syntax"), Some("4"));
+        assert_eq!(children(get(&entries, "Define").unwrap()).len(), 2);
+        let device = children(get(&entries, "SectionDevice").unwrap());
+        assert_eq!(device[0].0, "Head phones");
+        assert_eq!(children(get(&entries, "Seq").unwrap()).len(), 4);
+        assert_eq!(control_name("iface=CARD,name='Headphone Jack'"), "Headphone Jack");
+    }
+
+    #[test]
+    fn evaluates_card_config() {
+        let ucm_dir = fixture();
+        let card = laptop();
+        let config = find_card_config(&ucm_dir, &card).expect("HDA-Intel config in fixture");
+        let ucm = read_ucm_config(&ucm_dir, &config, &card);
+
+        assert_eq!(ucm.config_file, "conf.d/HDA-Intel/HDA-Intel.conf");
+        assert_eq!(ucm.syntax, Some(6));
+        assert!(ucm.errors.is_empty(), "{:?}", ucm.errors);
+        assert_eq!(ucm.boot_sequence, vec!["cset \"name='Master Playback Switch' on\""]);
+        assert_eq!(ucm.unresolved, vec!["RegexMatch cfg-dmics:[34]"]);
+
+        assert_eq!(ucm.verbs.len(), 1);
+        let hifi = &ucm.verbs[0];
+        assert_eq!(hifi.name, "HiFi");
+        assert_eq!(hifi.comment.as_deref(), Some("Play HiFi quality Music"));
+        assert_eq!(hifi.enable_sequence, vec!["disdevall \"\""]);
+        assert_eq!(hifi.values.get("TQ").map(String::as_str), Some("HiFi"));
+
+        let names: Vec<&str> = hifi.devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["Speaker", "Headphones", "Mic1", "HDMI1", "HDMI2"]);
+
+        let speaker = &hifi.devices[0];
+        assert_eq!(speaker.playback_pcm.as_deref(), Some("hw:PCH"));
+        assert_eq!(speaker.conflicting_devices, ["Headphones"]);
+        assert_eq!(speaker.enable_sequence, vec!["cset \"name='Speaker Playback Switch' on\""]);
+        assert_eq!(speaker.disable_sequence, vec!["cset \"name='Speaker Playback Switch' off\""]);
+
+        // The speaker switch exists, so the headphones get the alternative mixer element
+        assert_eq!(hifi.devices[1].values.get("PlaybackMixerElem").map(String::as_str), Some("Headphone"));
+
+        let dmic = &hifi.devices[2];
+        assert_eq!(dmic.capture_pcm.as_deref(), Some("hw:PCH,6"));
+        assert_eq!(dmic.values.get("CaptureChannels"), None);
+
+        // Macro expansion via the <...> include
+        assert_eq!(hifi.devices[4].playback_pcm.as_deref(), Some("hw:PCH,4"));
+        assert_eq!(hifi.devices[4].comment.as_deref(), Some("HDMI/DisplayPort 2 Output"));
+
+        assert_eq!(hifi.modifiers.len(), 1);
+        assert_eq!(hifi.modifiers[0].supported_devices, ["Speaker", "Headphones"]);
+    }
+
+    #[test]
+    fn unsupported_card_reports_error() {
+        let ucm_dir = fixture();
+        let card = CardVars { controls: Vec::new(), ..laptop() };
+        let config = find_card_config(&ucm_dir, &card).unwrap();
+        let ucm = read_ucm_config(&ucm_dir, &config, &card);
+
+        assert!(ucm.verbs.is_empty());
+        assert_eq!(ucm.errors, vec!["UCM is not supported for this HDA model (HDA Intel PCH at 0xf1330000 irq 147)"]);
+    }
+}
@@ -0,0 +1,367 @@
+//! Parser for USB audio stream descriptions in `/proc/asound/cardN/streamM`
+//!
+//! The snd-usb-audio driver writes one `streamM` file per PCM device. Each
+//! lists the playback and capture interfaces with every alternate setting the
+//! device advertises, plus a status block while a stream is running. Reading
+//! it never opens the device, so it is safe while audio is playing.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// One `streamM` file: a USB audio PCM device with both directions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsbStream {
+    /// Stream index, matching the PCM device number
+    pub stream: u32,
+    /// First line, e.g. "Focusrite Scarlett 2i2 USB at usb-0000:00:14.0-2, high speed : USB Audio"
+    pub description: String,
+    pub playback: Option<UsbStreamDirection>,
+    pub capture: Option<UsbStreamDirection>,
+}
+
+/// Status and altsets of one direction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsbStreamDirection {
+    pub status: Option<UsbStreamStatus>,
+    pub altsets: Vec<UsbAltset>,
+}
+
+/// The "Status:" block; details are only present while the stream runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsbStreamStatus {
+    /// "Running" or "Stop"
+    pub state: String,
+    pub interface: Option<u32>,
+    pub altset: Option<u32>,
+    pub packet_size: Option<u32>,
+    /// Momentary frequency in Hz as measured from the feedback endpoint
+    pub momentary_freq_hz: Option<u32>,
+    /// Raw fixed-point frequency, e.g. "0x6.0000"
+    pub momentary_freq_raw: Option<String>,
+    pub feedback_format: Option<String>,
+    /// Nominal rate of the running altset closest to the momentary frequency
+    pub current_rate: Option<u32>,
+}
+
+/// One interface/alternate setting with its format description
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsbAltset {
+    pub interface: u32,
+    pub altset: u32,
+    pub formats: Vec<String>,
+    pub channels: Option<u32>,
+    /// Endpoint address and direction, e.g. "0x01 (1 OUT)"
+    pub endpoint: Option<String>,
+    /// Isochronous sync type: ASYNC, ADAPTIVE, SYNC or NONE
+    pub sync_type: Option<String>,
+    /// Discrete rates; empty when the altset has a continuous range
+    pub rates: Vec<u32>,
+    pub rate_min: Option<u32>,
+    pub rate_max: Option<u32>,
+    pub continuous_rates: bool,
+    pub data_packet_interval_us: Option<u32>,
+    pub bits: Option<u32>,
+    pub channel_map: Vec<String>,
+    pub sync_endpoint: Option<String>,
+    pub sync_ep_interface: Option<u32>,
+    pub sync_ep_altset: Option<u32>,
+    pub implicit_feedback: Option<bool>,
+}
+
+impl UsbStreamDirection {
+    /// Highest channel count across all altsets
+    pub fn max_channels(&self) -> Option<u32> {
+        self.altsets.iter().filter_map(|a| a.channels).max()
+    }
+
+    /// Sorted union of every discrete rate and continuous range bound
+    pub fn sample_rates(&self) -> Vec<u32> {
+        let mut rates: Vec<u32> = self.altsets.iter()
+            .flat_map(|a| a.rates.iter().copied().chain(a.rate_min).chain(a.rate_max))
+            .collect();
+        rates.sort_unstable();
+        rates.dedup();
+        rates
+    }
+
+    /// Every distinct format across all altsets, in first-seen order
+    pub fn formats(&self) -> Vec<String> {
+        let mut formats = Vec::new();
+        for format in self.altsets.iter().flat_map(|a| a.formats.iter()) {
+            if !formats.contains(format) {
+                formats.push(format.clone());
+            }
+        }
+        formats
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.as_ref().is_some_and(|s| s.state == "Running")
+    }
+}
+
+/// Read and parse `<card_path>/stream<stream>`, if it exists
+pub fn read_usb_stream(card_path: &Path, stream: u32) -> Option<UsbStream> {
+    let content = fs::read_to_string(card_path.join(format!("stream{}", stream))).ok()?;
+    let mut parsed = parse_usb_stream(&content);
+    parsed.stream = stream;
+    Some(parsed)
+}
+
+/// Parse the contents of a `streamM` file
+pub fn parse_usb_stream(content: &str) -> UsbStream {
+    let mut stream = UsbStream::default();
+    // (is_playback, direction) of the section being read
+    let mut current: Option<(bool, UsbStreamDirection)> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        // Section headers and the description are not indented
+        if !line.starts_with(' ') {
+            match trimmed {
+                "Playback:" | "Capture:" => {
+                    finish(&mut stream, current.take());
+                    current = Some((trimmed == "Playback:", UsbStreamDirection::default()));
+                }
+                _ if stream.description.is_empty() => stream.description = trimmed.to_string(),
+                _ => {}
+            }
+            continue;
+        }
+
+        let Some((_, ref mut direction)) = current else { continue };
+
+        if let Some(state) = trimmed.strip_prefix("Status:") {
+            direction.status = Some(UsbStreamStatus {
+                state: state.trim().to_string(),
+                ..Default::default()
+            });
+        } else if let Some((key, value)) = trimmed.split_once(" = ") {
+            // Running status details: "Interface = 1", "Momentary freq = 48000 Hz (0x6.0000)"
+            if let Some(ref mut status) = direction.status {
+                parse_status_field(status, key.trim(), value.trim());
+            }
+        } else if let Some(interface) = trimmed.strip_prefix("Interface ").and_then(|v| v.trim().parse().ok()) {
+            direction.altsets.push(UsbAltset { interface, ..Default::default() });
+        } else if let Some(altset) = trimmed.strip_prefix("Altset ").and_then(|v| v.trim().parse().ok()) {
+            if let Some(current_altset) = direction.altsets.last_mut() {
+                current_altset.altset = altset;
+            }
+        } else if let Some((key, value)) = trimmed.split_once(':') {
+            if let Some(current_altset) = direction.altsets.last_mut() {
+                parse_altset_field(current_altset, key.trim(), value.trim());
+            }
+        }
+    }
+
+    finish(&mut stream, current);
+    stream
+}
+
+/// Store a finished Playback/Capture section on the stream
+fn finish(stream: &mut UsbStream, current: Option<(bool, UsbStreamDirection)>) {
+    if let Some((is_playback, mut direction)) = current {
+        if let Some(ref mut status) = direction.status {
+            status.current_rate = nominal_rate(status, &direction.altsets);
+        }
+        if is_playback {
+            stream.playback = Some(direction);
+        } else {
+            stream.capture = Some(direction);
+        }
+    }
+}
+
+fn parse_status_field(status: &mut UsbStreamStatus, key: &str, value: &str) {
+    match key {
+        "Interface" => status.interface = value.parse().ok(),
+        "Altset" => status.altset = value.parse().ok(),
+        "Packet Size" => status.packet_size = value.parse().ok(),
+        "Momentary freq" => {
+            // "48000 Hz (0x6.0000)"
+            status.momentary_freq_hz = value.split_whitespace().next().and_then(|v| v.parse().ok());
+            status.momentary_freq_raw = value.split_once('(')
+                .map(|(_, raw)| raw.trim_end_matches(')').to_string());
+        }
+        "Feedback Format" => status.feedback_format = Some(value.to_string()),
+        _ => {}
+    }
+}
+
+fn parse_altset_field(altset: &mut UsbAltset, key: &str, value: &str) {
+    match key {
+        "Format" => altset.formats = value.split_whitespace().map(str::to_string).collect(),
+        "Channels" => altset.channels = value.parse().ok(),
+        "Endpoint" => {
+            // "0x01 (1 OUT) (ASYNC)" on current kernels, "1 OUT (ASYNC)" on older ones
+            let (endpoint, sync_type) = split_trailing_sync_type(value);
+            altset.endpoint = Some(endpoint);
+            altset.sync_type = sync_type;
+        }
+        "Rates" => {
+            if let Some(range) = value.strip_suffix("(continuous)") {
+                let mut bounds = range.split('-').map(|v| v.trim().parse::<u32>().ok());
+                altset.rate_min = bounds.next().flatten();
+                altset.rate_max = bounds.next().flatten();
+                altset.continuous_rates = true;
+            } else {
+                altset.rates = value.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+            }
+        }
+        "Data packet interval" => {
+            altset.data_packet_interval_us = value.trim_end_matches("us").trim().parse().ok();
+        }
+        "Bits" => altset.bits = value.parse().ok(),
+        "Channel map" => altset.channel_map = value.split_whitespace().map(str::to_string).collect(),
+        "Sync Endpoint" => altset.sync_endpoint = Some(value.to_string()),
+        "Sync EP Interface" => altset.sync_ep_interface = value.parse().ok(),
+        "Sync EP Altset" => altset.sync_ep_altset = value.parse().ok(),
+        "Implicit Feedback Mode" => altset.implicit_feedback = Some(value.eq_ignore_ascii_case("yes")),
+        _ => {}
+    }
+}
+
+/// Split "0x01 (1 OUT) (ASYNC)" into ("0x01 (1 OUT)", Some("ASYNC"))
+fn split_trailing_sync_type(value: &str) -> (String, Option<String>) {
+    if let Some(open) = value.rfind('(') {
+        let candidate = value[open + 1..].trim_end_matches(')');
+        if matches!(candidate, "ASYNC" | "ADAPTIVE" | "SYNC" | "NONE") {
+            return (value[..open].trim().to_string(), Some(candidate.to_string()));
+        }
+    }
+    (value.to_string(), None)
+}
+
+/// Pick the running altset's rate closest to the measured momentary frequency
+fn nominal_rate(status: &UsbStreamStatus, altsets: &[UsbAltset]) -> Option<u32> {
+    let freq = status.momentary_freq_hz?;
+    let running = altsets.iter()
+        .find(|a| Some(a.interface) == status.interface && Some(a.altset) == status.altset);
+
+    let candidates: Vec<u32> = match running {
+        Some(altset) if !altset.rates.is_empty() => altset.rates.clone(),
+        // Continuous ranges have no nominal list; the measured rate is the best answer
+        _ => return Some(freq),
+    };
+
+    candidates.into_iter().min_by_key(|&rate| rate.abs_diff(freq))
+}
+
+/// Print the altsets of both directions as part of a verbose device listing
+pub fn print_usb_stream(stream: &UsbStream) {
+    println!("├─ USB Stream: {}", stream.description);
+
+    for (label, direction) in [("Playback", &stream.playback), ("Capture", &stream.capture)] {
+        let Some(direction) = direction else { continue };
+
+        match direction.status {
+            Some(ref status) if direction.is_running() => {
+                let freq = status.momentary_freq_hz.map_or("unknown".to_string(), |f| format!("{} Hz", f));
+                let rate = status.current_rate.map_or("unknown".to_string(), |r| format!("{} Hz", r));
+                println!("│  ├─ {}: Running (interface {}, altset {}, rate {}, momentary {})",
+                    label,
+                    status.interface.map_or("?".to_string(), |i| i.to_string()),
+                    status.altset.map_or("?".to_string(), |a| a.to_string()),
+                    rate,
+                    freq,
+                );
+            }
+            Some(ref status) => println!("│  ├─ {}: {}", label, status.state),
+            None => println!("│  ├─ {}:", label),
+        }
+
+        for altset in &direction.altsets {
+            let rates = if altset.continuous_rates {
+                format!("{}-{} Hz", altset.rate_min.unwrap_or(0), altset.rate_max.unwrap_or(0))
+            } else {
+                format!("{:?} Hz", altset.rates)
+            };
+            println!("│  │  ├─ if {} alt {}: {} {}ch {}bit {} {} {}",
+                altset.interface,
+                altset.altset,
+                altset.formats.join("/"),
+                altset.channels.unwrap_or(0),
+                altset.bits.unwrap_or(0),
+                rates,
+                altset.sync_type.as_deref().unwrap_or("-"),
+                altset.data_packet_interval_us.map_or(String::new(), |us| format!("{} us", us)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_uac2_stream_with_two_altsets() {
+        let content = "\
+Focusrite Scarlett 2i2 USB at usb-0000:00:14.0-2, high speed : USB Audio
+
+Playback:
+  Status: Running
+    Interface = 1
+    Altset = 2
+    Packet Size = 24
+    Momentary freq = 44101 Hz (0x5.8338)
+    Feedback Format = 16.16
+  Interface 1
+    Altset 1
+    Format: S32_LE
+    Channels: 2
+    Endpoint: 0x01 (1 OUT) (ASYNC)
+    Rates: 44100, 48000, 88200, 96000
+    Data packet interval: 125 us
+    Bits: 24
+    Channel map: FL FR
+    Sync Endpoint: 0x81 (1 IN)
+    Sync EP Interface: 1
+    Sync EP Altset: 1
+    Implicit Feedback Mode: No
+  Interface 1
+    Altset 2
+    Format: S16_LE
+    Channels: 2
+    Endpoint: 0x01 (1 OUT) (ASYNC)
+    Rates: 44100, 48000
+    Data packet interval: 125 us
+    Bits: 16
+    Channel map: FL FR
+";
+        let stream = parse_usb_stream(content);
+        assert!(stream.description.starts_with("Focusrite Scarlett 2i2 USB"));
+        assert!(stream.capture.is_none());
+
+        let playback = stream.playback.unwrap();
+        assert_eq!(playback.altsets.len(), 2);
+        let first = &playback.altsets[0];
+        assert_eq!((first.interface, first.altset, first.bits), (1, 1, Some(24)));
+        assert_eq!(first.endpoint.as_deref(), Some("0x01 (1 OUT)"));
+        assert_eq!(first.sync_type.as_deref(), Some("ASYNC"));
+        assert_eq!(first.data_packet_interval_us, Some(125));
+        assert_eq!(first.implicit_feedback, Some(false));
+        assert_eq!((first.sync_ep_interface, first.sync_ep_altset), (Some(1), Some(1)));
+        let second = &playback.altsets[1];
+        assert_eq!((second.altset, second.bits), (2, Some(16)));
+        assert_eq!(second.formats, ["S16_LE"]);
+        assert_eq!(second.rates, [44100, 48000]);
+
+        assert_eq!(playback.formats(), ["S32_LE", "S16_LE"]);
+        assert_eq!(playback.sample_rates(), [44100, 48000, 88200, 96000]);
+        assert_eq!(playback.max_channels(), Some(2));
+
+        // The running altset's nominal rate nearest the measured frequency
+        assert!(playback.is_running());
+        let status = playback.status.unwrap();
+        assert_eq!((status.interface, status.altset, status.packet_size), (Some(1), Some(2), Some(24)));
+        assert_eq!(status.momentary_freq_raw.as_deref(), Some("0x5.8338"));
+        assert_eq!(status.feedback_format.as_deref(), Some("16.16"));
+        assert_eq!(status.current_rate, Some(44100));
+    }
+}
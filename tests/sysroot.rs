@@ -0,0 +1,110 @@
+//! Interrogations of recorded `/proc` and `/sys` trees
+//!
+//! Each directory under `tests/fixtures/sysroot` is a trimmed copy of a real
+//! machine's procfs (and, where the card identity matters, sysfs). Only the
+//! `proc` backend can run against them; CPAL and ALSA must report themselves
+//! unavailable rather than mixing in the machine running the tests.
+
+use std::path::Path;
+
+use audio_interrogator::devices::{self, card_filter_index, resolve_card_number, BackendRegistry, BackendStatus, ProbeOptions};
+use audio_interrogator::{owners, AudioDeviceInfo, SysRoot, SystemAudioInfo};
+
+fn fixture(machine: &str) -> SysRoot {
+    SysRoot::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysroot").join(machine))
+}
+
+fn interrogate(machine: &str) -> SystemAudioInfo {
+    let options = ProbeOptions { sysroot: fixture(machine), ..Default::default() };
+    devices::interrogate(&BackendRegistry::with_builtin(), &options)
+}
+
+fn device<'a>(info: &'a SystemAudioInfo, name: &str) -> &'a AudioDeviceInfo {
+    info.find_device(name).unwrap_or_else(|| panic!("{} not found", name))
+}
+
+#[test]
+fn live_backends_stay_out_of_a_sysroot() {
+    let info = interrogate("hda");
+    let statuses: Vec<(&str, &BackendStatus)> = info.backends.iter().map(|b| (b.backend.as_str(), &b.status)).collect();
+    let unavailable = BackendStatus::Unavailable { reason: "probes the running system, not a sysroot".to_string() };
+    assert_eq!(statuses, [("cpal", &unavailable), ("alsa", &unavailable), ("proc", &BackendStatus::Ok)]);
+    assert_eq!(info.default_output, None);
+}
+
+#[test]
+fn hda_laptop() {
+    let info = interrogate("hda");
+    assert_eq!(info.devices.len(), 1);
+
+    let analog = device(&info, "hw:0,0");
+    assert_eq!(analog.device_type, "Input/Output");
+    assert_eq!(analog.stable_id.as_deref(), Some("pci-0000:00:1f.3-pcm0"));
+    assert_eq!(analog.hardware.as_ref().and_then(|h| h.kernel_driver.as_deref()), Some("snd_hda_intel"));
+    assert!(analog.in_use);
+    let running = analog.subdevices.iter().find(|s| s.in_use).unwrap();
+    assert_eq!(running.direction, "playback");
+    assert_eq!(running.hw_params.as_ref().and_then(|p| p.rate), Some(48000));
+
+    assert_eq!(info.cards.len(), 1);
+    assert_eq!(info.cards[0].id, "PCH");
+    assert_eq!(info.cards[0].hda_codecs[0].name, "Realtek ALC3246");
+
+    let report = owners::find_owners(&fixture("hda"), None);
+    assert_eq!(report.nodes.len(), 1);
+    assert_eq!(report.nodes[0].node, "/dev/snd/pcmC0D0p");
+    let process = &report.nodes[0].processes[0];
+    assert_eq!((process.pid, process.command.as_str()), (1873, "/usr/bin/pipewire"));
+    assert_eq!(process.user.as_deref(), Some("alice"));
+}
+
+#[test]
+fn usb_interface() {
+    let info = interrogate("usb");
+    let scarlett = device(&info, "hw:1,0");
+    assert_eq!(scarlett.stable_id.as_deref(), Some("usb-1235_8210-Y8XXXXX0123456-pcm0"));
+    assert_eq!(scarlett.supported_sample_rates, [44100, 48000, 88200, 96000, 176400, 192000]);
+    assert_eq!((scarlett.input_channels, scarlett.output_channels), (2, 2));
+    assert!(scarlett.in_use);
+
+    let stream = scarlett.usb_stream.as_ref().unwrap();
+    let capture = stream.capture.as_ref().and_then(|c| c.status.as_ref()).unwrap();
+    assert_eq!(capture.state, "Running");
+    assert_eq!(capture.current_rate, Some(48000));
+
+    let root = fixture("usb");
+    assert_eq!(card_filter_index(&root, Some("USB")).unwrap(), Some(1));
+    assert_eq!(resolve_card_number(&root, "usb-1235_8210-Y8XXXXX0123456"), Some(1));
+    assert!(card_filter_index(&root, Some("PCH")).is_err());
+}
+
+#[test]
+fn hdmi_outputs() {
+    let info = interrogate("hdmi");
+    assert_eq!(info.devices.len(), 3);
+    assert_eq!(info.cards[0].elds.len(), 3);
+
+    let sink = |name| device(&info, name).hdmi_sink.as_ref().and_then(|e| e.monitor_name.as_deref());
+    assert_eq!(sink("hw:0,3"), Some("DELL U2720Q"));
+    assert_eq!(sink("hw:0,7"), Some("SONY TV"));
+    assert_eq!(sink("hw:0,8"), None);
+    assert!(device(&info, "hw:0,8").hdmi_sink.as_ref().is_some_and(|e| !e.monitor_present));
+}
+
+#[test]
+fn soc_board() {
+    let info = interrogate("soc");
+    let ids: Vec<&str> = info.cards.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(ids, ["Headphones", "sndrpihifiberry"]);
+
+    // No sysfs in the recording, so there is no hardware identity to build a stable ID from
+    let headphones = device(&info, "hw:0,0");
+    assert!(headphones.hardware.is_none());
+    assert_eq!(headphones.subdevices.len(), 4);
+    assert!(!headphones.in_use);
+
+    let dac = device(&info, "hw:1,0");
+    assert_eq!(dac.device_type, "Output");
+    assert!(dac.in_use);
+    assert_eq!(card_filter_index(&fixture("soc"), Some("sndrpihifiberry")).unwrap(), Some(1));
+}